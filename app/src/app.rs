@@ -1,15 +1,26 @@
 use crate::app::actions::AppAction;
+use crate::app::central::CentralView;
 use crate::runtime::{file_picker, Runtime};
-use crate::windows::{ToggleableWindowState, WindowState};
+use crate::widgets::enum_select::EnumSelect;
+use crate::widgets::gallery::{Gallery, GalleryState};
+use crate::widgets::on_this_day::{OnThisDay, OnThisDayState};
+use crate::windows::{EntryExport, EntryImport, ToggleableWindowState, WindowState};
 use eframe::{App, Frame};
-use egui::{CentralPanel, Context, FontDefinitions, TopBottomPanel, Ui};
+use egui::{CentralPanel, Context, FontDefinitions, TopBottomPanel, Ui, Widget};
 use egui_notify::Toasts;
 
 pub mod actions;
+mod central;
 
 #[derive(Default, serde::Deserialize, serde::Serialize)]
 pub struct ApodexApp {
     windows: WindowState,
+    #[serde(default)]
+    central_view: CentralView,
+    #[serde(default)]
+    on_this_day: OnThisDayState,
+    #[serde(default)]
+    gallery: GalleryState,
     #[serde(default, skip)]
     pub actions: actions::AppActions,
     #[serde(default, skip)]
@@ -26,6 +37,13 @@ impl ApodexApp {
             .storage
             .and_then(|storage| eframe::get_value::<Self>(storage, eframe::APP_KEY))
             .unwrap_or_default();
+        app.runtime
+            .data
+            .set_current_date_mode(app.windows.scrape.current_date_mode());
+        app.runtime.data.load_missing_dates();
+        app.runtime
+            .media
+            .set_prefer_standard(app.windows.details.prefer_standard);
         app.runtime.data_load_included_html();
         app
     }
@@ -58,8 +76,13 @@ impl App for ApodexApp {
         }
     }
 
+    /// Also called periodically, which keeps the access times in the media cache up to date
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
+        if let Err(err) = self.runtime.media.flush_cache() {
+            self.toasts
+                .error(format!("Failed to save media access times: {err}"));
+        }
     }
 }
 
@@ -73,11 +96,28 @@ impl ApodexApp {
             self.windows.export.toggle_button(ui);
             self.windows.data.toggle_button(ui);
             self.windows.details.toggle_button(ui);
+            self.windows.duplicates.toggle_button(ui);
             self.windows.scrape.toggle_button(ui);
+            self.windows.cache.toggle_button(ui);
+            ui.separator();
+            EnumSelect::new(&mut self.central_view, "central_view_select").ui(ui);
         });
     }
 
-    fn show_central_panel(&mut self, _ui: &mut Ui) {}
+    fn show_central_panel(&mut self, ui: &mut Ui) {
+        match self.central_view {
+            CentralView::OnThisDay => {
+                OnThisDay::new(&mut self.on_this_day, &self.actions, &mut self.runtime).ui(ui)
+            }
+            CentralView::Gallery => Gallery::new(
+                &mut self.gallery,
+                &mut self.windows.data.table_state,
+                &self.actions,
+                &mut self.runtime,
+            )
+            .ui(ui),
+        };
+    }
 }
 
 // Actions
@@ -97,6 +137,7 @@ impl ApodexApp {
                 self.toasts.warning(message);
             }
             AppAction::InsertHtml { date, html } => self.runtime.data.insert_html(date, html),
+            AppAction::RecordMissingDate(date) => self.runtime.data.record_missing_date(date)?,
         };
         Ok(())
     }
@@ -106,16 +147,65 @@ impl ApodexApp {
         action: file_picker::FilePickerAction,
     ) -> anyhow::Result<()> {
         match action.target() {
+            file_picker::PickTarget::ExportEntries => {
+                if let Some(path) = action.single_path() {
+                    match self.windows.export.entry_export() {
+                        EntryExport::Rows(options) => {
+                            self.runtime.data_export_entries(path, options)
+                        }
+                        EntryExport::Sqlite => self.runtime.data_export_sqlite(path),
+                    }
+                }
+            }
+            file_picker::PickTarget::ExportEpub => {
+                if let Some(path) = action.single_path() {
+                    let options = self.windows.export.epub_options();
+                    self.runtime.data_export_epub(path, options);
+                }
+            }
+            file_picker::PickTarget::ExportMarkdownVault => {
+                if let Some(path) = action.single_path() {
+                    self.runtime.data_export_markdown_vault(path);
+                }
+            }
+            file_picker::PickTarget::ImportEntries => {
+                if let Some(path) = action.single_path() {
+                    match self.windows.import.entry_import() {
+                        EntryImport::Rows(format) => self.runtime.data_import_entries(path, format),
+                        EntryImport::Sqlite => self.runtime.data_import_sqlite(path),
+                    }
+                }
+            }
             file_picker::PickTarget::LoadHtmlArchive => {
                 if let Some(path) = action.single_path() {
                     self.runtime.data_load_html(path);
                 }
             }
+            file_picker::PickTarget::LoadHtmlPatch => {
+                if let Some(path) = action.single_path() {
+                    self.runtime.data_load_html_patch(path);
+                }
+            }
+            file_picker::PickTarget::LoadShardedHtmlArchive => {
+                if let Some(path) = action.single_path() {
+                    self.runtime.data_load_sharded_html(path);
+                }
+            }
             file_picker::PickTarget::SaveHtmlArchive => {
                 if let Some(path) = action.single_path() {
                     self.runtime.data_save_html(path);
                 }
             }
+            file_picker::PickTarget::SaveHtmlPatch => {
+                if let Some(path) = action.single_path() {
+                    self.runtime.data_save_html_patch(path);
+                }
+            }
+            file_picker::PickTarget::SaveShardedHtmlArchive => {
+                if let Some(path) = action.single_path() {
+                    self.runtime.data_save_sharded_html(path);
+                }
+            }
         }
 
         Ok(())
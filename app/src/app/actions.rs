@@ -9,6 +9,7 @@ pub enum AppAction {
     FilePickerAction(FilePickerAction),
     InsertHtml { date: ApodDate, html: String },
     OpenAndFocusWindow(WindowId),
+    RecordMissingDate(ApodDate),
     ToastError(String),
     ToastSuccess(String),
     ToastWarning(String),
@@ -50,6 +51,10 @@ impl AppActions {
         self.push_action(AppAction::OpenAndFocusWindow(window_id));
     }
 
+    pub fn record_missing_date(&self, date: ApodDate) {
+        self.push_action(AppAction::RecordMissingDate(date));
+    }
+
     pub fn toast_error(&self, message: impl Into<String>) {
         self.push_action(AppAction::ToastError(message.into()));
     }
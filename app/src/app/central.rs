@@ -0,0 +1,21 @@
+use std::fmt::{Display, Formatter};
+use strum_macros::EnumIter;
+
+/// What is shown in the central panel
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, EnumIter, serde::Serialize, serde::Deserialize,
+)]
+pub enum CentralView {
+    #[default]
+    OnThisDay,
+    Gallery,
+}
+
+impl Display for CentralView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CentralView::OnThisDay => write!(f, "On this day"),
+            CentralView::Gallery => write!(f, "Gallery"),
+        }
+    }
+}
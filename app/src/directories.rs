@@ -13,6 +13,23 @@ pub fn eframe_save_file_path() -> PathBuf {
     data_dir_path().join("app.ron")
 }
 
+pub fn missing_dates_file_path() -> PathBuf {
+    data_dir_path().join("missing-dates.bin")
+}
+
+/// Entries imported from CSV, JSON or SQLite, merged over the parsed HTML archive on load
+pub fn imported_entries_file_path() -> PathBuf {
+    data_dir_path().join("imported-entries.bin")
+}
+
+pub fn download_queue_file_path() -> PathBuf {
+    data_dir_path().join("download-queue.bin")
+}
+
 pub fn heed_cache_dir() -> PathBuf {
     data_dir_path().join("media")
 }
+
+pub fn media_index_file_path() -> PathBuf {
+    data_dir_path().join("media-index.bin")
+}
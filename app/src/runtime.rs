@@ -1,11 +1,19 @@
 use crate::app::actions::AppActions;
+use crate::runtime::apod_media::CacheOperation;
+use crate::widgets::animation_player::AnimationPlayback;
 use apodex::date::ApodDate;
+use apodex::exporting::epub::EpubOptions;
+use apodex::exporting::ExportOptions;
+use apodex::importing::ImportFormat;
+use apodex::media::download::DownloadOptions;
 use egui::Ui;
 use std::path::Path;
 
+pub mod animation;
 pub mod apod_data;
-mod apod_media;
+pub mod apod_media;
 pub mod file_picker;
+pub mod media_indexer;
 mod scraper;
 mod task;
 
@@ -14,6 +22,7 @@ pub struct Runtime {
     pub data: apod_data::ApodData,
     pub file_picker: file_picker::FilePicker,
     pub media: apod_media::ApodMedia,
+    pub indexer: media_indexer::MediaIndexer,
     pub scraper: scraper::Scraper,
 }
 
@@ -30,6 +39,7 @@ impl Default for Runtime {
             data: Default::default(),
             file_picker: Default::default(),
             media: Default::default(),
+            indexer: Default::default(),
             scraper: Default::default(),
         }
     }
@@ -41,6 +51,7 @@ impl Runtime {
         self.data.update(ctx, self.tokio.handle(), actions);
         self.scraper.update(ctx, self.tokio.handle(), actions);
         self.media.update(ctx, self.tokio.handle(), actions);
+        self.indexer.update(ctx, self.tokio.handle(), actions);
     }
 }
 
@@ -58,9 +69,81 @@ impl Runtime {
         self.data.start_save_html(self.tokio.handle(), path);
     }
 
-    pub fn show_image(&mut self, ui: &mut Ui, date: ApodDate) {
+    pub fn data_load_sharded_html(&mut self, dir: impl AsRef<Path>) {
+        self.data.start_load_sharded_html(self.tokio.handle(), dir);
+    }
+
+    pub fn data_save_sharded_html(&mut self, dir: impl AsRef<Path>) {
+        self.data.start_save_sharded_html(self.tokio.handle(), dir);
+    }
+
+    pub fn data_load_html_patch(&mut self, path: impl AsRef<Path>) {
+        self.data.start_load_html_patch(self.tokio.handle(), path);
+    }
+
+    pub fn data_save_html_patch(&mut self, path: impl AsRef<Path>) {
+        self.data.start_save_html_patch(self.tokio.handle(), path);
+    }
+
+    pub fn data_export_entries(&mut self, path: impl AsRef<Path>, options: ExportOptions) {
+        self.data
+            .start_export_entries(self.tokio.handle(), path, options);
+    }
+
+    pub fn data_export_sqlite(&mut self, path: impl AsRef<Path>) {
+        self.data.start_export_sqlite(self.tokio.handle(), path);
+    }
+
+    pub fn data_export_epub(&mut self, path: impl AsRef<Path>, options: EpubOptions) {
+        let media = self.media.cache();
+        self.data
+            .start_export_epub(self.tokio.handle(), path, options, media);
+    }
+
+    pub fn data_export_markdown_vault(&mut self, dir: impl AsRef<Path>) {
+        let media = self.media.cache();
+        self.data
+            .start_export_markdown_vault(self.tokio.handle(), dir, media);
+    }
+
+    pub fn data_import_entries(&mut self, path: impl AsRef<Path>, format: ImportFormat) {
+        self.data
+            .start_import_entries(self.tokio.handle(), path, format);
+    }
+
+    pub fn data_import_sqlite(&mut self, path: impl AsRef<Path>) {
+        self.data.start_import_sqlite(self.tokio.handle(), path);
+    }
+
+    pub fn data_forget_imported_entries(&mut self) {
+        self.data.start_forget_imported_entries(self.tokio.handle());
+    }
+
+    pub fn media_cache_operation(&mut self, operation: CacheOperation) {
+        self.media
+            .start_cache_operation(self.tokio.handle(), operation);
+    }
+
+    pub fn media_download_start(&mut self, options: DownloadOptions, concurrency: usize) {
+        let entries = self.data.entry_archive().clone();
+        self.media
+            .start_download(self.tokio.handle(), entries, options, concurrency);
+    }
+
+    pub fn media_download_resume(&mut self, retry_failed: bool, concurrency: usize) {
+        let entries = self.data.entry_archive().clone();
+        self.media
+            .resume_download(self.tokio.handle(), entries, retry_failed, concurrency);
+    }
+
+    pub fn media_index_update(&mut self) {
+        let cache = self.media.cache();
+        self.indexer.start_update(self.tokio.handle(), cache);
+    }
+
+    pub fn show_image(&mut self, ui: &mut Ui, date: ApodDate, playback: &mut AnimationPlayback) {
         if let Some(entry) = self.data.get_entry(date) {
-            self.media.show_image(ui, entry);
+            self.media.show_image(ui, entry, playback);
         } else {
             ui.small("No entry found");
         }
@@ -0,0 +1,173 @@
+use apodex::date::ApodDate;
+use apodex::media::MediaVariant;
+use egui::{ColorImage, Context, TextureHandle, TextureOptions};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Frames, ImageFormat};
+use lru::LruCache;
+use std::io::Cursor;
+use std::time::Duration;
+
+/// Decoded frames of all loaded animations together stay below this, frames beyond it
+/// are dropped and the least recently shown animations are unloaded
+pub const ANIMATION_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+/// Browsers show GIF frames with shorter delays, often 0, for this long
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+
+pub type MediaKey = (ApodDate, MediaVariant);
+
+/// Frames of an animated GIF, PNG or WebP, decoded in the background
+pub struct DecodedAnimation {
+    pub frames: Vec<(ColorImage, Duration)>,
+    /// Whether frames were dropped to stay within the budget
+    pub truncated: bool,
+}
+
+impl DecodedAnimation {
+    /// None for still images and media that can't be decoded.
+    /// Stops decoding once the frames would take more than `budget` bytes.
+    pub fn decode(data: &[u8], budget: usize) -> Option<Self> {
+        let frames = match image::guess_format(data).ok()? {
+            ImageFormat::Gif => GifDecoder::new(Cursor::new(data)).ok()?.into_frames(),
+            ImageFormat::Png => {
+                let decoder = PngDecoder::new(Cursor::new(data)).ok()?;
+                if !decoder.is_apng().ok()? {
+                    return None;
+                }
+                decoder.apng().ok()?.into_frames()
+            }
+            ImageFormat::WebP => {
+                let decoder = WebPDecoder::new(Cursor::new(data)).ok()?;
+                if !decoder.has_animation() {
+                    return None;
+                }
+                decoder.into_frames()
+            }
+            _ => return None,
+        };
+        let animation = Self::collect(frames, budget);
+        (animation.frames.len() > 1).then_some(animation)
+    }
+
+    /// Frames after a decoding error are dropped like frames over the budget
+    fn collect(frames: Frames, budget: usize) -> Self {
+        let mut animation = Self {
+            frames: Vec::new(),
+            truncated: false,
+        };
+        let mut size = 0;
+        for frame in frames {
+            let Ok(frame) = frame else {
+                animation.truncated = true;
+                break;
+            };
+            let delay = Duration::from(frame.delay());
+            let buffer = frame.into_buffer();
+            size += buffer.as_raw().len();
+            if size > budget {
+                animation.truncated = true;
+                break;
+            }
+
+            let dimensions = [buffer.width() as usize, buffer.height() as usize];
+            let image = ColorImage::from_rgba_unmultiplied(dimensions, buffer.as_raw());
+            let delay = if delay < MIN_FRAME_DELAY {
+                DEFAULT_FRAME_DELAY
+            } else {
+                delay
+            };
+            animation.frames.push((image, delay));
+        }
+        animation
+    }
+}
+
+pub struct AnimationFrame {
+    pub texture: TextureHandle,
+    pub delay: Duration,
+}
+
+/// Frames uploaded as textures, always at least two
+pub struct Animation {
+    pub frames: Vec<AnimationFrame>,
+    pub truncated: bool,
+    size: usize,
+}
+
+impl Animation {
+    pub fn upload(ctx: &Context, (date, variant): MediaKey, decoded: DecodedAnimation) -> Self {
+        let size = decoded
+            .frames
+            .iter()
+            .map(|(image, _)| image.pixels.len() * 4)
+            .sum();
+        let frames = decoded
+            .frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, (image, delay))| AnimationFrame {
+                texture: ctx.load_texture(
+                    format!("apod-{}-{}-frame-{}", date, variant, i),
+                    image,
+                    TextureOptions::LINEAR,
+                ),
+                delay,
+            })
+            .collect();
+        Self {
+            frames,
+            truncated: decoded.truncated,
+            size,
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.frames[0].texture.aspect_ratio()
+    }
+}
+
+/// Loaded animations within [`ANIMATION_MEMORY_BUDGET`]
+pub struct AnimationCache {
+    animations: LruCache<MediaKey, Animation>,
+    size: usize,
+}
+
+impl Default for AnimationCache {
+    fn default() -> Self {
+        Self {
+            animations: LruCache::unbounded(),
+            size: 0,
+        }
+    }
+}
+
+impl AnimationCache {
+    pub fn get(&mut self, key: &MediaKey) -> Option<&Animation> {
+        self.animations.get(key)
+    }
+
+    pub fn contains(&self, key: &MediaKey) -> bool {
+        self.animations.contains(key)
+    }
+
+    /// Unloads the least recently shown animations until the new one fits
+    pub fn insert(&mut self, key: MediaKey, animation: Animation) {
+        if let Some(old) = self.animations.pop(&key) {
+            self.size -= old.size;
+        }
+        while self.size + animation.size > ANIMATION_MEMORY_BUDGET
+            && let Some((_, old)) = self.animations.pop_lru()
+        {
+            self.size -= old.size;
+        }
+        self.size += animation.size;
+        self.animations.put(key, animation);
+    }
+
+    pub fn clear(&mut self) {
+        self.animations.clear();
+        self.size = 0;
+    }
+}
@@ -1,15 +1,25 @@
 use crate::app::actions::AppActions;
+use crate::directories::{imported_entries_file_path, missing_dates_file_path};
 use crate::runtime::task::{TaskContext, TaskHandler};
 use crate::runtime::RuntimeSystem;
 use apodex::archiving::html::ArchiveHtml;
+use apodex::archiving::patch::ArchivePatch;
 use apodex::archiving::{Archive, ArchiveError};
-use apodex::date::ApodDate;
+use apodex::date::missing::{MissingDateRegistry, MissingReason};
+use apodex::date::{ApodDate, CurrentDateMode};
+use apodex::exporting::epub::EpubOptions;
+use apodex::exporting::ExportOptions;
+use apodex::importing::{ImportFormat, ImportReport, RowIssue};
+use apodex::media::heed::HeedMediaCache;
+use apodex::parsing::extras::parse_extras;
+use apodex::parsing::quality_control::quality_control;
 use apodex::parsing::quality_control::QualityWarning;
 use apodex::parsing::ParseError;
+use apodex::sqlite::SqliteStore;
 use apodex::ApodEntry;
 use egui::Context;
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 struct LoadedHtmlArchive {
@@ -17,6 +27,18 @@ struct LoadedHtmlArchive {
     entry_archive: Archive<ApodEntry>,
     parse_warnings: HashMap<ApodDate, HashSet<QualityWarning>>,
     parse_errors: HashMap<ApodDate, ParseError>,
+    /// Number of persisted imported entries that were merged
+    imported_entries: usize,
+    /// Why the persisted imported entries could not be loaded
+    imported_entries_error: Option<String>,
+}
+
+pub struct ImportSummary {
+    pub rows: usize,
+    pub imported: usize,
+    pub issues: Vec<RowIssue>,
+    /// Where the imported entries were saved to be loaded again on startup
+    pub saved_to: PathBuf,
 }
 
 pub struct ApodData {
@@ -27,6 +49,17 @@ pub struct ApodData {
     parse_errors: HashMap<ApodDate, ParseError>,
     load_html_task: TaskHandler<Result<LoadedHtmlArchive, ArchiveError>>,
     save_html_task: TaskHandler<Result<(), ArchiveError>>,
+    /// Resolves to a success message
+    export_task: TaskHandler<anyhow::Result<String>>,
+    /// Resolves to the report, the file the entries were saved to and how many it holds
+    import_task: TaskHandler<anyhow::Result<(ImportReport, PathBuf, usize)>>,
+    last_import: Option<ImportSummary>,
+    /// Persisted imported entries, loaded with every HTML archive
+    imported_entries: usize,
+    imported_entries_error: Option<String>,
+    /// Set from the scrape window, which persists it
+    current_date_mode: CurrentDateMode,
+    missing_dates: MissingDateRegistry,
 }
 
 impl Default for ApodData {
@@ -39,12 +72,23 @@ impl Default for ApodData {
             parse_errors: HashMap::new(),
             load_html_task: TaskHandler::default(),
             save_html_task: TaskHandler::default(),
+            export_task: TaskHandler::default(),
+            import_task: TaskHandler::default(),
+            last_import: None,
+            imported_entries: 0,
+            imported_entries_error: None,
+            current_date_mode: CurrentDateMode::default(),
+            missing_dates: MissingDateRegistry::default(),
         }
     }
 }
 
 impl ApodData {
     pub fn insert_html(&mut self, date: ApodDate, html: String) {
+        if self.missing_dates.remove(date) {
+            let _ = self.save_missing_dates();
+        }
+
         let verbose_result = apodex::parsing::verbose::parse_html_verbose(date, &html);
         if let Some(entry) = verbose_result.entry {
             self.entry_archive.push(entry);
@@ -79,6 +123,49 @@ impl ApodData {
         });
     }
 
+    pub fn start_load_sharded_html(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        dir: impl AsRef<Path>,
+    ) {
+        let dir = dir.as_ref().to_owned();
+        self.load_html_task.spawn(handle, |ctx| async move {
+            ctx.set_status("Loading sharded HTML archive...");
+            let html_archive: Archive<ArchiveHtml> = Archive::load_sharded(&dir)?;
+            Self::load_html(ctx, html_archive)
+        });
+    }
+
+    /// Applies an update patch on top of the included snapshot and loads the result
+    pub fn start_load_html_patch(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        path: impl AsRef<Path>,
+    ) {
+        let path = path.as_ref().to_owned();
+        self.load_html_task.spawn(handle, |ctx| async move {
+            ctx.set_status("Loading update patch...");
+            let patch: ArchivePatch<ArchiveHtml> = ArchivePatch::load(&path)?;
+            let mut html_archive: Archive<ArchiveHtml> = Archive::load_included_html_archive();
+            ctx.set_status(format!("Applying {} updated entries...", patch.len()));
+            html_archive.apply_patch(&patch)?;
+            Self::load_html(ctx, html_archive)
+        });
+    }
+
+    /// Deletes the persisted imported entries and parses the HTML archive again without them
+    pub fn start_forget_imported_entries(&mut self, handle: &tokio::runtime::Handle) {
+        let html_archive = self.html_archive.clone();
+        self.load_html_task.spawn(handle, |ctx| async move {
+            ctx.set_status("Forgetting imported entries...");
+            match std::fs::remove_file(imported_entries_file_path()) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+            Self::load_html(ctx, html_archive)
+        });
+    }
+
     fn load_html(
         ctx: TaskContext,
         archive: Archive<ArchiveHtml>,
@@ -110,11 +197,41 @@ impl ApodData {
             ))
         }
 
+        // Imported entries replace parsed ones, like when they were imported
+        let path = imported_entries_file_path();
+        let mut imported_entries = 0;
+        let mut imported_entries_error = None;
+        match Archive::<ApodEntry>::load(&path) {
+            Ok(imported) => {
+                ctx.set_status(format!("Merging {} imported entries...", imported.len()));
+                for (date, entry) in imported.iter() {
+                    let warnings = quality_control(entry);
+                    if warnings.is_empty() {
+                        parse_warnings.remove(date);
+                    } else {
+                        parse_warnings.insert(*date, warnings);
+                    }
+                    parse_errors.remove(date);
+                }
+                imported_entries = imported.len();
+                entry_archive.extend(imported);
+            }
+            Err(ArchiveError::IO(err)) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                imported_entries_error = Some(format!(
+                    "Failed to load imported entries from {}: {err}",
+                    path.display()
+                ));
+            }
+        }
+
         Ok(LoadedHtmlArchive {
             html_archive: archive,
             entry_archive,
             parse_warnings,
             parse_errors,
+            imported_entries,
+            imported_entries_error,
         })
     }
 
@@ -125,6 +242,8 @@ impl ApodData {
             self.entry_archive = loaded.entry_archive;
             self.parse_warnings = loaded.parse_warnings;
             self.parse_errors = loaded.parse_errors;
+            self.imported_entries = loaded.imported_entries;
+            self.imported_entries_error = loaded.imported_entries_error;
             self.last_update = Instant::now();
         }))
     }
@@ -139,10 +258,224 @@ impl ApodData {
         });
     }
 
+    pub fn start_save_sharded_html(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        dir: impl AsRef<Path>,
+    ) {
+        let dir = dir.as_ref().to_owned();
+        let archive = self.html_archive.clone();
+        self.save_html_task.spawn(handle, async move |ctx| {
+            ctx.set_status("Compressing changed shards, this might take a bit...");
+            archive.save_sharded(&dir, 22)?;
+            Ok(())
+        });
+    }
+
+    /// Saves all entries that were added or changed since the included snapshot
+    pub fn start_save_html_patch(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        path: impl AsRef<Path>,
+    ) {
+        let path = path.as_ref().to_owned();
+        let archive = self.html_archive.clone();
+        self.save_html_task.spawn(handle, async move |ctx| {
+            ctx.set_status("Comparing with included archive...");
+            let base = Archive::load_included_html_archive();
+            let patch = archive.make_patch(&base);
+            ctx.set_status(format!("Compressing {} updated entries...", patch.len()));
+            patch.save(&path, 22)?;
+            Ok(())
+        });
+    }
+
     pub fn poll_save_html(&mut self) -> Option<Result<(), ArchiveError>> {
         self.save_html_task.poll()
     }
 
+    pub fn start_export_entries(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        path: impl AsRef<Path>,
+        options: ExportOptions,
+    ) {
+        let path = path.as_ref().to_owned();
+        let archive = self.entry_archive.clone();
+        self.export_task.spawn(handle, async move |ctx| {
+            ctx.set_status(format!("Exporting {} entries...", archive.len()));
+            apodex::exporting::export_entries_to_file(&archive, &options, &path)?;
+            Ok(format!("Exported {} entries!", archive.len()))
+        });
+    }
+
+    /// Writes entries, parse errors and the credits and links of the HTML pages into a database
+    pub fn start_export_sqlite(&mut self, handle: &tokio::runtime::Handle, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_owned();
+        let entry_archive = self.entry_archive.clone();
+        let html_archive = self.html_archive.clone();
+        let parse_errors = self.parse_errors.clone();
+        self.export_task.spawn(handle, async move |ctx| {
+            ctx.set_status("Parsing credits and links...");
+            let extras = html_archive
+                .iter()
+                .filter(|(date, _)| entry_archive.has_date(**date))
+                .map(|(date, html)| (*date, parse_extras(&html.html)))
+                .collect::<HashMap<_, _>>();
+
+            ctx.set_status(format!("Writing {} entries...", entry_archive.len()));
+            let mut store = SqliteStore::open(&path)?;
+            store.write_entries(&entry_archive)?;
+            store.write_errors(&parse_errors)?;
+            store.write_extras(&extras)?;
+            Ok(format!("Exported {} entries!", entry_archive.len()))
+        });
+    }
+
+    /// Builds an e-book of the date range with images from the media cache
+    pub fn start_export_epub(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        path: impl AsRef<Path>,
+        options: EpubOptions,
+        media: HeedMediaCache,
+    ) {
+        let path = path.as_ref().to_owned();
+        let in_range = |date: &ApodDate| options.range.contains(*date);
+        let mut entry_archive = Archive::default();
+        for (_, entry) in self.entry_archive.iter().filter(|(date, _)| in_range(date)) {
+            entry_archive.push(entry.clone());
+        }
+        let html_pages: Vec<(ApodDate, String)> = self
+            .html_archive
+            .iter()
+            .filter(|(date, _)| in_range(date))
+            .map(|(date, html)| (*date, html.html.clone()))
+            .collect();
+
+        self.export_task.spawn(handle, async move |ctx| {
+            ctx.set_status("Parsing credits...");
+            let credits = html_pages
+                .iter()
+                .filter_map(|(date, html)| Some((*date, parse_extras(html).credits?)))
+                .collect::<HashMap<_, _>>();
+
+            ctx.set_status(format!("Writing {} entries...", entry_archive.len()));
+            let report = apodex::exporting::epub::export_epub_to_file(
+                &entry_archive,
+                &credits,
+                Some(&media),
+                &options,
+                &path,
+            )?;
+
+            if report.missing_media.is_empty() {
+                Ok(format!("Exported {} entries!", report.entries))
+            } else {
+                Ok(format!(
+                    "Exported {} entries, {} without cached media",
+                    report.entries,
+                    report.missing_media.len()
+                ))
+            }
+        });
+    }
+
+    /// Writes a note per entry with cross-links, next to the cached images
+    pub fn start_export_markdown_vault(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        dir: impl AsRef<Path>,
+        media: HeedMediaCache,
+    ) {
+        let dir = dir.as_ref().to_owned();
+        let entry_archive = self.entry_archive.clone();
+        let html_archive = self.html_archive.clone();
+        self.export_task.spawn(handle, async move |ctx| {
+            ctx.set_status("Parsing explanation links...");
+            let extras = html_archive
+                .iter()
+                .filter(|(date, _)| entry_archive.has_date(**date))
+                .map(|(date, html)| (*date, parse_extras(&html.html)))
+                .collect::<HashMap<_, _>>();
+
+            ctx.set_status(format!("Writing {} notes...", entry_archive.len()));
+            let report = apodex::exporting::markdown::export_markdown_vault(
+                &entry_archive,
+                &extras,
+                Some(&media),
+                &dir,
+            )?;
+            Ok(format!(
+                "Exported {} notes with {} images!",
+                report.notes, report.images
+            ))
+        });
+    }
+
+    pub fn poll_export(&mut self) -> Option<anyhow::Result<String>> {
+        self.export_task.poll()
+    }
+
+    pub fn start_import_entries(
+        &mut self,
+        handle: &tokio::runtime::Handle,
+        path: impl AsRef<Path>,
+        format: ImportFormat,
+    ) {
+        let path = path.as_ref().to_owned();
+        self.import_task.spawn(handle, async move |ctx| {
+            ctx.set_status("Importing entries...");
+            let report = apodex::importing::import_entries_from_file(format, &path)?;
+            ctx.set_status("Saving imported entries...");
+            let (saved_to, total) = save_imported_entries(&report.archive)?;
+            Ok((report, saved_to, total))
+        });
+    }
+
+    pub fn start_import_sqlite(&mut self, handle: &tokio::runtime::Handle, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_owned();
+        self.import_task.spawn(handle, async move |ctx| {
+            ctx.set_status("Reading entries from database...");
+            let archive = SqliteStore::open_read_only(&path)?.read_entries()?;
+            ctx.set_status("Saving imported entries...");
+            let (saved_to, total) = save_imported_entries(&archive)?;
+            let report = ImportReport {
+                rows: archive.len(),
+                archive,
+                issues: Vec::new(),
+            };
+            Ok((report, saved_to, total))
+        });
+    }
+
+    /// Imported entries replace existing entries of the same date
+    pub fn poll_import(&mut self) -> Option<anyhow::Result<()>> {
+        let result = self.import_task.poll()?;
+        Some(result.map(|(report, saved_to, total)| {
+            let imported = report.archive.len();
+            for (date, entry) in report.archive.iter() {
+                let warnings = quality_control(entry);
+                if warnings.is_empty() {
+                    self.parse_warnings.remove(date);
+                } else {
+                    self.parse_warnings.insert(*date, warnings);
+                }
+                self.parse_errors.remove(date);
+            }
+            self.entry_archive.extend(report.archive);
+            self.last_import = Some(ImportSummary {
+                rows: report.rows,
+                imported,
+                issues: report.issues,
+                saved_to,
+            });
+            self.imported_entries = total;
+            self.imported_entries_error = None;
+            self.last_update = Instant::now();
+        }))
+    }
+
     pub fn load_busy(&self) -> bool {
         self.load_html_task.is_busy()
     }
@@ -159,14 +492,52 @@ impl ApodData {
         self.save_html_task.status()
     }
 
+    pub fn export_busy(&self) -> bool {
+        self.export_task.is_busy()
+    }
+
+    pub fn export_status(&self) -> Option<String> {
+        self.export_task.status()
+    }
+
+    pub fn import_busy(&self) -> bool {
+        self.import_task.is_busy()
+    }
+
+    pub fn import_status(&self) -> Option<String> {
+        self.import_task.status()
+    }
+
+    pub fn last_import(&self) -> Option<&ImportSummary> {
+        self.last_import.as_ref()
+    }
+
+    /// Number of persisted imported entries, which replace parsed entries on every load
+    pub fn imported_entries(&self) -> usize {
+        self.imported_entries
+    }
+
+    pub fn imported_entries_error(&self) -> Option<&str> {
+        self.imported_entries_error.as_deref()
+    }
+
     pub fn get_html(&self, date: ApodDate) -> Option<&ArchiveHtml> {
         self.html_archive.get(date)
     }
 
+    pub fn entry_archive(&self) -> &Archive<ApodEntry> {
+        &self.entry_archive
+    }
+
     pub fn get_entry(&self, date: ApodDate) -> Option<&ApodEntry> {
         self.entry_archive.get(date)
     }
 
+    /// Entries sharing the month and day across all years, oldest first
+    pub fn entries_on_this_day(&self, month: u32, day: u32) -> Vec<&ApodEntry> {
+        self.entry_archive.on_this_day(month, day)
+    }
+
     pub fn get_warnings(&self, date: ApodDate) -> Option<&HashSet<QualityWarning>> {
         self.parse_warnings.get(&date)
     }
@@ -187,23 +558,103 @@ impl ApodData {
         self.entry_archive.latest_date()
     }
 
+    pub fn set_current_date_mode(&mut self, mode: CurrentDateMode) {
+        if self.current_date_mode != mode {
+            self.current_date_mode = mode;
+            self.last_update = Instant::now();
+        }
+    }
+
+    /// The latest date with a possible entry
+    pub fn current_date(&self) -> ApodDate {
+        ApodDate::current(self.current_date_mode)
+    }
+
+    pub fn total_apod_days(&self) -> u32 {
+        ApodDate::total_apod_days(self.current_date_mode, &self.missing_dates)
+    }
+
+    /// All dates up to the current one that can have an entry
+    pub fn iter_till_today(&self) -> impl Iterator<Item = ApodDate> + use<> {
+        ApodDate::iter_till_today(self.current_date_mode, &self.missing_dates)
+    }
+
     pub fn has_missing(&self) -> bool {
-        (self.html_archive.len() as u32) < ApodDate::total_apod_days()
+        (self.html_archive.len() as u32) < self.total_apod_days()
     }
 
     pub fn missing_count(&self) -> usize {
-        ApodDate::total_apod_days() as usize - self.html_archive.len()
+        (self.total_apod_days() as usize).saturating_sub(self.html_archive.len())
+    }
+
+    /// Confirms that the date has no entry, so it is no longer counted or downloaded as missing
+    pub fn record_missing_date(&mut self, date: ApodDate) -> std::io::Result<()> {
+        self.missing_dates.record(date, MissingReason::NotFound);
+        self.last_update = Instant::now();
+        self.save_missing_dates()
+    }
+
+    pub fn missing_dates(&self) -> &MissingDateRegistry {
+        &self.missing_dates
+    }
+
+    pub fn set_reverify_after_days(&mut self, days: Option<u32>) -> std::io::Result<()> {
+        self.missing_dates.set_reverify_after_days(days);
+        self.last_update = Instant::now();
+        self.save_missing_dates()
+    }
+
+    /// Replaces the registry of missing dates with the persisted one, keeps the default if there is none yet
+    pub fn load_missing_dates(&mut self) {
+        if let Ok(registry) = MissingDateRegistry::load(&missing_dates_file_path()) {
+            self.missing_dates = registry;
+            self.last_update = Instant::now();
+        }
+    }
+
+    fn save_missing_dates(&self) -> std::io::Result<()> {
+        let path = missing_dates_file_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.missing_dates.save(&path)
     }
 
     pub fn iter_missing(&self) -> impl Iterator<Item = ApodDate> {
-        ApodDate::iter_till_today().filter(|date| !self.html_archive.has_date(*date))
+        self.iter_till_today()
+            .filter(|date| !self.html_archive.has_date(*date))
     }
 }
 
+/// Merges the entries into the previously imported ones,
+/// returns the file they were saved to and how many entries it holds
+fn save_imported_entries(archive: &Archive<ApodEntry>) -> anyhow::Result<(PathBuf, usize)> {
+    let path = imported_entries_file_path();
+    let mut imported = match Archive::<ApodEntry>::load(&path) {
+        Ok(imported) => imported,
+        Err(ArchiveError::IO(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            Archive::default()
+        }
+        Err(err) => return Err(err.into()),
+    };
+    imported.extend(archive.clone());
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    imported.save(&path, 3)?;
+    Ok((path, imported.len()))
+}
+
 impl RuntimeSystem for ApodData {
     fn update(&mut self, _ctx: &Context, _handle: &tokio::runtime::Handle, actions: &AppActions) {
         match self.poll_load_html() {
-            Some(Ok(())) => actions.toast_success("Data loaded successfully!"),
+            Some(Ok(())) => {
+                actions.toast_success("Data loaded successfully!");
+                if let Some(err) = &self.imported_entries_error {
+                    actions.toast_error(err.clone());
+                }
+            }
             Some(Err(err)) => actions.toast_error(format!("Error loading data: {}", err)),
             None => {}
         }
@@ -212,5 +663,26 @@ impl RuntimeSystem for ApodData {
             Some(Err(err)) => actions.toast_error(format!("Error saving data: {}", err)),
             None => {}
         }
+        match self.poll_export() {
+            Some(Ok(message)) => actions.toast_success(message),
+            Some(Err(err)) => actions.toast_error(format!("Error exporting entries: {}", err)),
+            None => {}
+        }
+        match self.poll_import() {
+            Some(Ok(())) => {
+                if let Some(summary) = &self.last_import {
+                    if summary.issues.is_empty() {
+                        actions.toast_success(format!("Imported {} entries!", summary.imported));
+                    } else {
+                        actions.toast_warning(format!(
+                            "Imported {} of {} entries, see import window for issues",
+                            summary.imported, summary.rows
+                        ));
+                    }
+                }
+            }
+            Some(Err(err)) => actions.toast_error(format!("Error importing entries: {}", err)),
+            None => {}
+        }
     }
 }
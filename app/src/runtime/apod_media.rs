@@ -1,47 +1,208 @@
 use crate::app::actions::AppActions;
-use crate::directories::heed_cache_dir;
-use crate::runtime::task::TaskHandler;
+use crate::directories::{download_queue_file_path, heed_cache_dir};
+use crate::runtime::animation::{
+    Animation, AnimationCache, DecodedAnimation, MediaKey, ANIMATION_MEMORY_BUDGET,
+};
+use crate::runtime::task::{TaskContext, TaskHandler};
 use crate::runtime::RuntimeSystem;
+use crate::widgets::animation_player::{AnimationPlayback, AnimationPlayer};
+use apodex::archiving::Archive;
 use apodex::client::reqwest::ReqwestClient;
 use apodex::client::{ApodClient, ClientError};
 use apodex::date::ApodDate;
+use apodex::media::download::{
+    BulkDownload, DownloadError, DownloadOptions, DownloadProgress, DownloadQueue,
+};
 use apodex::media::heed::HeedMediaCache;
-use apodex::media::{MediaCache, MediaEntry};
+use apodex::media::thumbnail::{get_or_generate_thumbnail, ThumbnailOptions};
+use apodex::media::verify::{verify_cache, VerifyReport};
+use apodex::media::video::mp4_duration;
+use apodex::media::{
+    EvictionPolicy, MediaCache, MediaCacheStats, MediaEntry, MediaType, MediaVariant,
+};
 use apodex::ApodEntry;
-use egui::{ColorImage, Context, TextureHandle, TextureId, TextureOptions, Vec2};
+use egui::{ColorImage, Context, TextureHandle, TextureId, TextureOptions, Vec2, Widget};
+use egui_phosphor::regular::PLAY_CIRCLE;
 use lru::LruCache;
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
 use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::runtime::Handle;
 
+/// Thumbnails decoded per background task
+const THUMBNAIL_BATCH: usize = 8;
+/// Older requests are dropped, e.g. when scrolling quickly through the gallery
+const THUMBNAIL_QUEUE_LIMIT: usize = 64;
+const CACHE_INITIAL_SIZE_MB: usize = 2048;
+/// The cache grows up to this size, then the least recently viewed media is evicted
+const CACHE_MAX_SIZE_MB: usize = 16384;
+
+pub enum CacheOperation {
+    Refresh,
+    Evict(EvictionPolicy),
+    Clear,
+    /// Checks all cached media, then hashes intact entries cached before hashes were stored
+    Verify {
+        delete_corrupt: bool,
+        /// Adds corrupt full size media to the saved download job
+        redownload: bool,
+    },
+}
+
+struct CacheOutcome {
+    removed: usize,
+    verify_report: Option<VerifyReport>,
+    usage: CacheUsage,
+}
+
+enum DownloadStart {
+    New(DownloadOptions),
+    Resume { retry_failed: bool },
+}
+
+pub struct CacheUsage {
+    pub stats: MediaCacheStats,
+    pub disk_size: u64,
+    pub used_size: u64,
+    pub map_size: usize,
+    pub max_size_mb: usize,
+}
+
 pub struct ApodMedia {
     heed_cache: HeedMediaCache,
-    texture_cache: LruCache<ApodDate, TextureHandle>,
+    /// With the type of the media, to request its animation again after it was unloaded
+    texture_cache: LruCache<(ApodDate, MediaVariant), (TextureHandle, MediaType)>,
+    animations: AnimationCache,
+    /// Media that may be animated by its type but has a single frame, never decoded again
+    still_images: HashSet<MediaKey>,
+    animation_task: TaskHandler<(MediaKey, Option<DecodedAnimation>)>,
+    /// Only the latest request is decoded, it's the one on screen
+    animation_request: Option<MediaKey>,
+    thumbnail_cache: LruCache<(ApodDate, u16), TextureHandle>,
+    thumbnail_options: ThumbnailOptions,
     client: ReqwestClient,
     fetch_task: TaskHandler<Result<Option<MediaEntry>, ClientError>>,
-    current_fetch: Option<ApodEntry>,
-    queue: VecDeque<ApodEntry>,
+    current_fetch: Option<(ApodEntry, MediaVariant)>,
+    queue: VecDeque<(ApodEntry, MediaVariant)>,
+    /// Fetch standard images and only load HD versions on demand
+    prefer_standard: bool,
+    /// Dates whose HD version was requested while preferring standard images
+    hd_requested: HashSet<ApodDate>,
+    thumbnail_task: TaskHandler<Vec<(ApodDate, u16, Option<ColorImage>)>>,
+    thumbnail_queue: VecDeque<(ApodDate, u16)>,
+    thumbnail_loading: Vec<(ApodDate, u16)>,
+    /// Durations of cached MP4 videos, None for dates without one
+    video_durations: HashMap<ApodDate, Option<Duration>>,
+    duration_task: TaskHandler<(ApodDate, Option<Duration>)>,
+    /// Only the latest request is read, it's the one on screen
+    duration_request: Option<ApodDate>,
+    /// Dates without cached media or with media that can't be decoded
+    thumbnail_unavailable: HashSet<ApodDate>,
+    /// Media that could not be fetched, not requested again until restart
+    fetch_failed: HashSet<(ApodDate, MediaVariant)>,
+    cache_task: TaskHandler<anyhow::Result<CacheOutcome>>,
+    cache_usage: Option<CacheUsage>,
+    verify_report: Option<VerifyReport>,
+    download_task: TaskHandler<Result<DownloadProgress, DownloadError>>,
+    /// Updated by the running download
+    download_progress: Arc<Mutex<DownloadProgress>>,
+    /// The persisted download job, to resume it or retry its failures
+    saved_download: Option<DownloadQueue>,
 }
 
 impl Default for ApodMedia {
     fn default() -> Self {
-        let heed_cache = HeedMediaCache::new("media", heed_cache_dir(), 2048).unwrap();
+        let heed_cache = HeedMediaCache::new("media", heed_cache_dir(), CACHE_INITIAL_SIZE_MB)
+            .unwrap()
+            .with_max_size_mb(CACHE_MAX_SIZE_MB);
         let texture_cache = LruCache::new(NonZeroUsize::new(100).unwrap());
+        let thumbnail_cache = LruCache::new(NonZeroUsize::new(512).unwrap());
 
         Self {
             heed_cache,
             texture_cache,
+            animations: AnimationCache::default(),
+            still_images: HashSet::new(),
+            animation_task: Default::default(),
+            animation_request: None,
+            thumbnail_cache,
+            thumbnail_options: ThumbnailOptions::default(),
             client: Default::default(),
             fetch_task: Default::default(),
             current_fetch: None,
             queue: Default::default(),
+            prefer_standard: false,
+            hd_requested: HashSet::new(),
+            thumbnail_task: Default::default(),
+            thumbnail_queue: Default::default(),
+            thumbnail_loading: Vec::new(),
+            thumbnail_unavailable: HashSet::new(),
+            video_durations: HashMap::new(),
+            duration_task: Default::default(),
+            duration_request: None,
+            fetch_failed: HashSet::new(),
+            cache_task: Default::default(),
+            cache_usage: None,
+            verify_report: None,
+            download_task: Default::default(),
+            download_progress: Default::default(),
+            saved_download: load_saved_download(),
         }
     }
 }
 
 impl ApodMedia {
-    pub fn show_image(&mut self, ui: &mut egui::Ui, entry: &ApodEntry) {
-        if let Some((id, aspect)) = self.get_texture(ui.ctx(), entry) {
+    /// Animations are shown with playback controls once their frames are decoded
+    pub fn show_image(
+        &mut self,
+        ui: &mut egui::Ui,
+        entry: &ApodEntry,
+        playback: &mut AnimationPlayback,
+    ) {
+        if entry.media.kind().is_some_and(|kind| kind.is_video()) {
+            self.show_video(ui, entry);
+        } else if let Some((key, id, aspect)) = self.get_texture(ui.ctx(), entry) {
+            if let Some(animation) = self.animations.get(&key) {
+                AnimationPlayer::new(playback, key, animation).ui(ui);
+            } else {
+                let size = fit_to_bounds(ui.available_size(), aspect);
+                ui.image((id, size));
+            }
+        } else if let Some(status) = self.status() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(status);
+            });
+        } else {
+            ui.small("No media found");
+        }
+    }
+
+    /// The poster with a link to the video, full size videos are never fetched for this
+    fn show_video(&mut self, ui: &mut egui::Ui, entry: &ApodEntry) {
+        let video = entry.media.embedded_video();
+        ui.horizontal(|ui| {
+            let url = video
+                .as_ref()
+                .map(|video| video.watch_url())
+                .or_else(|| entry.media.highest_quality().map(str::to_string));
+            if let Some(url) = url {
+                let label = match &video {
+                    Some(video) => format!("{} Open video on {}", PLAY_CIRCLE, video.host),
+                    None => format!("{} Open video", PLAY_CIRCLE),
+                };
+                ui.hyperlink_to(label, url);
+            }
+            if let Some(duration) = self.video_duration(entry.date) {
+                ui.separator();
+                ui.label(humantime::format_duration(duration).to_string())
+                    .on_hover_text("Duration of the cached video");
+            }
+        });
+
+        if let Some((id, aspect)) = self.get_poster(ui.ctx(), entry) {
             let size = fit_to_bounds(ui.available_size(), aspect);
             ui.image((id, size));
         } else if let Some(status) = self.status() {
@@ -50,30 +211,232 @@ impl ApodMedia {
                 ui.label(status);
             });
         } else {
-            ui.small("No media found");
+            ui.small("No preview of this video");
         }
     }
 
-    pub fn get_texture(&mut self, ctx: &Context, entry: &ApodEntry) -> Option<(TextureId, f32)> {
-        if let Some(handle) = self.texture_cache.get(&entry.date) {
+    /// Fetches the poster of embedded videos, MP4 videos have none
+    fn get_poster(&mut self, ctx: &Context, entry: &ApodEntry) -> Option<(TextureId, f32)> {
+        let key = (entry.date, MediaVariant::Poster);
+        if let Some((handle, _)) = self.texture_cache.get(&key) {
             return Some((handle.id(), handle.aspect_ratio()));
         }
 
-        if let Some(media_entry) = self.heed_cache.get(entry.date).ok().flatten() {
-            let texture = Self::create_texture(ctx, entry.date, &media_entry.data)?;
+        if let Some(poster) = self.heed_cache.get_variant(key.0, key.1).ok().flatten() {
+            let texture = Self::create_texture(ctx, key, &poster.data)?;
             let id = texture.id();
             let aspect = texture.aspect_ratio();
-            self.texture_cache.put(entry.date, texture);
+            self.texture_cache.put(key, (texture, poster.media_type));
             return Some((id, aspect));
         }
 
-        if !self.queue.contains(entry) && self.current_fetch.as_ref() != Some(entry) {
-            self.queue.push_back(entry.clone());
+        if entry.media.embedded_video().is_some() {
+            self.request_variant(entry, MediaVariant::Poster);
+        }
+        None
+    }
+
+    /// Read from the header of a cached MP4 video in the background, once per date
+    fn video_duration(&mut self, date: ApodDate) -> Option<Duration> {
+        match self.video_durations.get(&date) {
+            Some(duration) => *duration,
+            None => {
+                self.duration_request = Some(date);
+                None
+            }
+        }
+    }
+
+    fn update_video_durations(&mut self, handle: &Handle) {
+        if let Some((date, duration)) = self.duration_task.poll() {
+            self.video_durations.insert(date, duration);
         }
 
+        if self.duration_task.is_busy() {
+            return;
+        }
+        let Some(date) = self.duration_request.take() else {
+            return;
+        };
+        if self.video_durations.contains_key(&date) {
+            return;
+        }
+
+        let cache = self.heed_cache.clone();
+        self.duration_task.spawn(handle, move |_ctx| async move {
+            let duration = MediaVariant::FULL_SIZE.iter().find_map(|variant| {
+                let video = cache.peek_variant(date, *variant).ok()??;
+                mp4_duration(&video.data)
+            });
+            (date, duration)
+        });
+    }
+
+    /// Shows an already cached HD version even when standard images are preferred.
+    /// Returns the cached media the texture was created from with it.
+    pub fn get_texture(
+        &mut self,
+        ctx: &Context,
+        entry: &ApodEntry,
+    ) -> Option<(MediaKey, TextureId, f32)> {
+        let wanted = self.wanted_variant(entry);
+        // Without an HD url both variants hold the same image, either one can be shown
+        let candidates: &[MediaVariant] = match wanted {
+            MediaVariant::Standard => &MediaVariant::FULL_SIZE,
+            _ if !entry.media.has_hd() => &MediaVariant::FULL_SIZE,
+            _ => &[MediaVariant::Hd],
+        };
+
+        for variant in candidates {
+            let key = (entry.date, *variant);
+            if let Some((handle, media_type)) = self.texture_cache.get(&key) {
+                let (id, aspect, media_type) = (handle.id(), handle.aspect_ratio(), *media_type);
+                self.request_animation(key, media_type);
+                return Some((key, id, aspect));
+            }
+
+            if let Some(media_entry) = self
+                .heed_cache
+                .get_variant(entry.date, *variant)
+                .ok()
+                .flatten()
+            {
+                let texture = Self::create_texture(ctx, key, &media_entry.data)?;
+                let id = texture.id();
+                let aspect = texture.aspect_ratio();
+                self.texture_cache
+                    .put(key, (texture, media_entry.media_type));
+                self.request_animation(key, media_entry.media_type);
+                return Some((key, id, aspect));
+            }
+        }
+
+        self.request_variant(entry, wanted);
         None
     }
 
+    /// The full size variant to fetch and show for the entry
+    fn wanted_variant(&self, entry: &ApodEntry) -> MediaVariant {
+        if self.prefer_standard && entry.media.has_hd() && !self.hd_requested.contains(&entry.date)
+        {
+            MediaVariant::Standard
+        } else {
+            MediaVariant::Hd
+        }
+    }
+
+    pub fn set_prefer_standard(&mut self, prefer_standard: bool) {
+        self.prefer_standard = prefer_standard;
+    }
+
+    /// Whether only the standard version is shown although there is an HD one
+    pub fn can_load_hd(&self, entry: &ApodEntry) -> bool {
+        self.wanted_variant(entry) == MediaVariant::Standard
+            && !self
+                .heed_cache
+                .variants(entry.date)
+                .is_ok_and(|variants| variants.contains(&MediaVariant::Hd))
+    }
+
+    /// Upgrades the entry to its HD version, fetching it if needed
+    pub fn load_hd(&mut self, entry: &ApodEntry) {
+        self.hd_requested.insert(entry.date);
+        self.request_variant(entry, MediaVariant::Hd);
+    }
+
+    /// Stored preview of at least the given size, generated from the cached media in the background.
+    /// Unlike [`Self::get_texture`] this never fetches missing media.
+    pub fn get_thumbnail(&mut self, date: ApodDate, pixels: f32) -> Option<(TextureId, f32)> {
+        let size = self.thumbnail_options.size_for(pixels)?;
+        let key = (date, size);
+        if let Some(handle) = self.thumbnail_cache.get(&key) {
+            return Some((handle.id(), handle.aspect_ratio()));
+        }
+
+        if !self.thumbnail_unavailable.contains(&date)
+            && !self.thumbnail_loading.contains(&key)
+            && !self.thumbnail_queue.contains(&key)
+        {
+            self.thumbnail_queue.push_back(key);
+            if self.thumbnail_queue.len() > THUMBNAIL_QUEUE_LIMIT {
+                self.thumbnail_queue.pop_front();
+            }
+        }
+
+        None
+    }
+
+    pub fn is_thumbnail_unavailable(&self, date: ApodDate) -> bool {
+        self.thumbnail_unavailable.contains(&date)
+    }
+
+    /// Does nothing if the media is already queued or failed before
+    fn request_variant(&mut self, entry: &ApodEntry, variant: MediaVariant) {
+        let request = (entry.clone(), variant);
+        if !self.fetch_failed.contains(&(entry.date, variant))
+            && !self.queue.contains(&request)
+            && self.current_fetch.as_ref() != Some(&request)
+        {
+            self.queue.push_back(request);
+        }
+    }
+
+    /// Decodes all frames if the media may be animated, the first frame is shown meanwhile.
+    /// Requested whenever the media is shown, so unloaded animations are decoded again.
+    fn request_animation(&mut self, key: MediaKey, media_type: MediaType) {
+        let may_be_animated = matches!(
+            media_type,
+            MediaType::ImageGIF | MediaType::ImagePNG | MediaType::ImageWEBP
+        );
+        if may_be_animated && !self.animations.contains(&key) && !self.still_images.contains(&key) {
+            self.animation_request = Some(key);
+        }
+    }
+
+    fn update_animations(&mut self, ctx: &Context, handle: &Handle) {
+        if let Some((key, decoded)) = self.animation_task.poll() {
+            match decoded {
+                Some(decoded) => self
+                    .animations
+                    .insert(key, Animation::upload(ctx, key, decoded)),
+                None => {
+                    self.still_images.insert(key);
+                }
+            }
+        }
+
+        if self.animation_task.is_busy() {
+            return;
+        }
+        let Some(key @ (date, variant)) = self.animation_request.take() else {
+            return;
+        };
+        // Requested again while it was decoded
+        if self.animations.contains(&key) || self.still_images.contains(&key) {
+            return;
+        }
+
+        let cache = self.heed_cache.clone();
+        self.animation_task.spawn(handle, move |_ctx| async move {
+            let decoded = cache
+                .peek_variant(date, variant)
+                .ok()
+                .flatten()
+                .and_then(|entry| DecodedAnimation::decode(&entry.data, ANIMATION_MEMORY_BUDGET));
+            (key, decoded)
+        });
+    }
+
+    /// Shares the underlying cache, e.g. for exports running in the background
+    pub fn cache(&self) -> HeedMediaCache {
+        self.heed_cache.clone()
+    }
+
+    /// Writes the access times of media viewed since the last flush
+    pub fn flush_cache(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.heed_cache.flush()
+    }
+
     pub fn is_busy(&self) -> bool {
         self.fetch_task.is_busy()
     }
@@ -82,15 +445,229 @@ impl ApodMedia {
         self.fetch_task.status()
     }
 
-    fn create_texture(ctx: &Context, date: ApodDate, data: &[u8]) -> Option<TextureHandle> {
-        let img = image::load_from_memory(data)
-            .ok()
-            .map(|img| img.to_rgba8())?;
-        let size = [img.width() as usize, img.height() as usize];
-        let pixels = img.into_raw();
+    pub fn cache_usage(&self) -> Option<&CacheUsage> {
+        self.cache_usage.as_ref()
+    }
+
+    /// The result of the last verification
+    pub fn verify_report(&self) -> Option<&VerifyReport> {
+        self.verify_report.as_ref()
+    }
+
+    pub fn cache_busy(&self) -> bool {
+        self.cache_task.is_busy()
+    }
+
+    pub fn cache_status(&self) -> Option<String> {
+        self.cache_task.status()
+    }
+
+    /// Runs the operation in the background and refreshes the usage afterward
+    pub fn start_cache_operation(&mut self, handle: &Handle, operation: CacheOperation) {
+        let mut cache = self.heed_cache.clone();
+        self.cache_task.spawn(handle, move |ctx| async move {
+            run_cache_operation(&mut cache, operation, &ctx).map_err(|err| anyhow::anyhow!(err))
+        });
+    }
+
+    pub fn download_busy(&self) -> bool {
+        self.download_task.is_busy()
+    }
+
+    pub fn download_status(&self) -> Option<String> {
+        self.download_task.status()
+    }
+
+    pub fn download_progress(&self) -> DownloadProgress {
+        self.download_progress.lock().unwrap().clone()
+    }
+
+    pub fn saved_download(&self) -> Option<&DownloadQueue> {
+        self.saved_download.as_ref()
+    }
+
+    /// Plans a new job over the entries, replacing the saved one
+    pub fn start_download(
+        &mut self,
+        handle: &Handle,
+        entries: Archive<ApodEntry>,
+        options: DownloadOptions,
+        concurrency: usize,
+    ) {
+        self.spawn_download(handle, entries, DownloadStart::New(options), concurrency);
+    }
+
+    /// Continues the saved job, optionally queueing its failures again
+    pub fn resume_download(
+        &mut self,
+        handle: &Handle,
+        entries: Archive<ApodEntry>,
+        retry_failed: bool,
+        concurrency: usize,
+    ) {
+        self.spawn_download(
+            handle,
+            entries,
+            DownloadStart::Resume { retry_failed },
+            concurrency,
+        );
+    }
+
+    fn spawn_download(
+        &mut self,
+        handle: &Handle,
+        entries: Archive<ApodEntry>,
+        start: DownloadStart,
+        concurrency: usize,
+    ) {
+        let client = self.client.clone();
+        let mut cache = self.heed_cache.clone();
+        let progress = self.download_progress.clone();
+        self.download_task.spawn(handle, move |ctx| async move {
+            ctx.set_status("Planning download");
+            let mut job = match start {
+                DownloadStart::New(options) => BulkDownload::plan(&entries, &cache, &options)?
+                    .with_state_file(download_queue_file_path()),
+                DownloadStart::Resume { retry_failed } => {
+                    let mut job = BulkDownload::load(download_queue_file_path())?;
+                    if retry_failed {
+                        job.retry_failed();
+                    }
+                    job
+                }
+            };
+            job = job.with_concurrency(concurrency);
+            job.save()?;
+
+            *progress.lock().unwrap() = job.progress();
+            ctx.set_status("Downloading media");
+            job.run(&client, &mut cache, &entries, |update| {
+                *progress.lock().unwrap() = update.clone();
+            })
+            .await
+        });
+    }
+
+    /// Cancels immediately, the downloads in flight stay queued in the saved job to resume later
+    pub fn abort_download(&mut self) {
+        self.download_task.abort();
+        self.saved_download = load_saved_download();
+    }
+
+    fn update_download_task(&mut self, actions: &AppActions) {
+        let Some(result) = self.download_task.poll() else {
+            return;
+        };
+
+        match result {
+            Ok(progress) if progress.failed > 0 => actions.toast_warning(format!(
+                "Downloaded {} files, {} failed",
+                progress.completed, progress.failed
+            )),
+            Ok(progress) => {
+                actions.toast_success(format!("Downloaded {} files", progress.completed))
+            }
+            Err(err) => actions.toast_error(format!("Download failed: {err}")),
+        }
+        self.thumbnail_unavailable.clear();
+        self.video_durations.clear();
+        self.saved_download = load_saved_download();
+    }
+
+    /// Drops everything loaded from the cache, e.g. after media was removed from it
+    fn clear_loaded(&mut self) {
+        self.texture_cache.clear();
+        self.animations.clear();
+        self.still_images.clear();
+        self.thumbnail_cache.clear();
+        self.thumbnail_unavailable.clear();
+        self.video_durations.clear();
+        self.hd_requested.clear();
+    }
+
+    fn update_cache_task(&mut self, actions: &AppActions) {
+        match self.cache_task.poll() {
+            Some(Ok(outcome)) => {
+                if let Some(report) = outcome.verify_report {
+                    if report.is_ok() {
+                        actions.toast_success(format!(
+                            "All {} cached files are intact",
+                            report.checked
+                        ));
+                    } else {
+                        actions.toast_warning(format!(
+                            "{} of {} cached files are corrupt",
+                            report.corrupt.len(),
+                            report.checked
+                        ));
+                    }
+                    self.verify_report = Some(report);
+                    self.saved_download = load_saved_download();
+                }
+                if outcome.removed > 0 {
+                    self.clear_loaded();
+                    actions.toast_success(format!("Removed {} cached files", outcome.removed));
+                }
+                self.cache_usage = Some(outcome.usage);
+            }
+            Some(Err(err)) => actions.toast_error(format!("Cache operation failed: {err}")),
+            None => {}
+        }
+    }
+
+    fn update_thumbnails(&mut self, ctx: &Context, handle: &Handle) {
+        if let Some(thumbnails) = self.thumbnail_task.poll() {
+            for (date, size, image) in thumbnails {
+                match image {
+                    Some(image) => {
+                        let texture = ctx.load_texture(
+                            format!("apod-thumbnail-{}-{}", date, size),
+                            image,
+                            TextureOptions::LINEAR,
+                        );
+                        self.thumbnail_cache.put((date, size), texture);
+                    }
+                    None => {
+                        self.thumbnail_unavailable.insert(date);
+                    }
+                }
+            }
+            self.thumbnail_loading.clear();
+        }
+
+        if self.thumbnail_task.is_busy() || self.thumbnail_queue.is_empty() {
+            return;
+        }
+
+        // Newest requests first, those are the ones currently visible
+        let count = self.thumbnail_queue.len().min(THUMBNAIL_BATCH);
+        self.thumbnail_loading = (0..count)
+            .filter_map(|_| self.thumbnail_queue.pop_back())
+            .collect();
+
+        let mut cache = self.heed_cache.clone();
+        let options = self.thumbnail_options.clone();
+        let keys = self.thumbnail_loading.clone();
+        self.thumbnail_task.spawn(handle, move |_ctx| async move {
+            keys.into_iter()
+                .map(|(date, size)| {
+                    let image = get_or_generate_thumbnail(&mut cache, date, size, &options)
+                        .ok()
+                        .and_then(|thumbnail| decode_image(&thumbnail.data));
+                    (date, size, image)
+                })
+                .collect()
+        });
+    }
+
+    fn create_texture(
+        ctx: &Context,
+        (date, variant): (ApodDate, MediaVariant),
+        data: &[u8],
+    ) -> Option<TextureHandle> {
         Some(ctx.load_texture(
-            format!("apod-{}", date),
-            ColorImage::from_rgba_unmultiplied(size, &pixels),
+            format!("apod-{}-{}", date, variant),
+            decode_image(data)?,
             TextureOptions::LINEAR,
         ))
     }
@@ -98,29 +675,37 @@ impl ApodMedia {
 
 impl RuntimeSystem for ApodMedia {
     fn update(&mut self, ctx: &Context, handle: &Handle, actions: &AppActions) {
+        self.update_thumbnails(ctx, handle);
+        self.update_animations(ctx, handle);
+        self.update_video_durations(handle);
+        self.update_cache_task(actions);
+        self.update_download_task(actions);
+
         if !self.is_busy() && self.queue.is_empty() {
             return;
         }
 
         if !self.is_busy()
-            && let Some(entry) = self.queue.pop_front()
+            && let Some((entry, variant)) = self.queue.pop_front()
         {
             let client = self.client.clone();
-            self.current_fetch = Some(entry.clone());
+            self.current_fetch = Some((entry.clone(), variant));
             self.fetch_task.spawn(handle, move |ctx| async move {
-                ctx.set_status(format!("Fetching media for {}", entry.date));
-                client.fetch_media(&entry).await
+                ctx.set_status(format!("Fetching {} media for {}", variant, entry.date));
+                client.fetch_media_variant(&entry, variant).await
             });
         } else {
-            let Some(entry) = self.current_fetch.as_ref() else {
+            let Some((entry, variant)) = self.current_fetch.as_ref() else {
                 return;
             };
+            let key = (entry.date, *variant);
 
             match self.fetch_task.poll() {
                 Some(Ok(media)) => {
                     if let Some(media) = media {
-                        if let Err(err) = self.heed_cache.store(
+                        if let Err(err) = self.heed_cache.store_variant(
                             entry.date,
+                            *variant,
                             media.data.as_slice(),
                             media.media_type,
                         ) {
@@ -129,8 +714,10 @@ impl RuntimeSystem for ApodMedia {
                                 entry.date
                             ));
                         }
-                        let Some(texture) = Self::create_texture(ctx, entry.date, &media.data)
-                        else {
+                        self.thumbnail_unavailable.remove(&entry.date);
+                        let Some(texture) = Self::create_texture(ctx, key, &media.data) else {
+                            // Fetching it again would only give the same data
+                            self.fetch_failed.insert(key);
                             actions.toast_warning(format!(
                                 "Failed to decode media for {}",
                                 entry.date
@@ -138,14 +725,18 @@ impl RuntimeSystem for ApodMedia {
                             self.current_fetch = None;
                             return;
                         };
-                        self.texture_cache.put(entry.date, texture);
+                        self.texture_cache.put(key, (texture, media.media_type));
+                        self.still_images.remove(&key);
+                        self.request_animation(key, media.media_type);
                     } else {
+                        self.fetch_failed.insert(key);
                         actions.toast_warning(format!("Media for {} not found", entry.date))
                     }
                     self.current_fetch = None;
                 }
                 Some(Err(err)) => {
                     actions.toast_error(format!("Failed to fetch media for {}: {err}", entry.date));
+                    self.fetch_failed.insert(key);
                     self.current_fetch = None;
                 }
                 None => {}
@@ -154,7 +745,80 @@ impl RuntimeSystem for ApodMedia {
     }
 }
 
-fn fit_to_bounds(bounds: Vec2, aspect: f32) -> Vec2 {
+fn load_saved_download() -> Option<DownloadQueue> {
+    BulkDownload::load(download_queue_file_path())
+        .ok()
+        .map(|job| job.queue().clone())
+}
+
+fn run_cache_operation(
+    cache: &mut HeedMediaCache,
+    operation: CacheOperation,
+    ctx: &TaskContext,
+) -> Result<CacheOutcome, Box<dyn Error + Send + Sync>> {
+    let mut verify_report = None;
+    let removed = match operation {
+        CacheOperation::Refresh => 0,
+        CacheOperation::Evict(policy) => {
+            ctx.set_status("Evicting media");
+            cache.evict(policy)?
+        }
+        CacheOperation::Clear => {
+            ctx.set_status("Clearing cache");
+            cache.clear()?
+        }
+        CacheOperation::Verify {
+            delete_corrupt,
+            redownload,
+        } => {
+            let report = verify_cache(cache, |checked, total| {
+                ctx.set_status(format!("Verifying media {checked}/{total}"));
+            })?;
+            let removed = if delete_corrupt {
+                report.delete_corrupt(cache)?
+            } else {
+                0
+            };
+            // Corrupt media that is kept would be hashed as it is now
+            if delete_corrupt || report.is_ok() {
+                cache.backfill_hashes()?;
+            }
+
+            if redownload && !report.is_ok() {
+                let path = download_queue_file_path();
+                let mut job = BulkDownload::load(&path).unwrap_or_else(|_| {
+                    BulkDownload::from_queue(DownloadQueue::default()).with_state_file(&path)
+                });
+                job.enqueue(report.downloadable());
+                job.save()?;
+            }
+            verify_report = Some(report);
+            removed
+        }
+    };
+
+    ctx.set_status("Calculating usage");
+    let usage = CacheUsage {
+        stats: cache.stats()?,
+        disk_size: cache.disk_size()?,
+        used_size: cache.used_size()?,
+        map_size: cache.map_size(),
+        max_size_mb: cache.max_size_mb(),
+    };
+    Ok(CacheOutcome {
+        removed,
+        verify_report,
+        usage,
+    })
+}
+
+fn decode_image(data: &[u8]) -> Option<ColorImage> {
+    let img = image::load_from_memory(data).ok()?.to_rgba8();
+    let size = [img.width() as usize, img.height() as usize];
+    Some(ColorImage::from_rgba_unmultiplied(size, &img.into_raw()))
+}
+
+pub fn fit_to_bounds(bounds: Vec2, aspect: f32) -> Vec2 {
     let width = bounds.x.min(bounds.y * aspect);
     let height = width / aspect;
     Vec2::new(width, height)
@@ -48,8 +48,16 @@ pub enum PickMode {
 
 #[derive(Debug, Copy, Clone)]
 pub enum PickTarget {
+    ExportEntries,
+    ExportEpub,
+    ExportMarkdownVault,
+    ImportEntries,
     LoadHtmlArchive,
+    LoadHtmlPatch,
+    LoadShardedHtmlArchive,
     SaveHtmlArchive,
+    SaveHtmlPatch,
+    SaveShardedHtmlArchive,
 }
 
 impl FilePicker {
@@ -70,6 +78,10 @@ impl FilePicker {
         self.open(target, PickMode::Single);
     }
 
+    pub fn open_directory(&mut self, target: PickTarget) {
+        self.open(target, PickMode::Directory);
+    }
+
     pub fn open_save(&mut self, target: PickTarget, default_name: impl Into<String>) {
         self.open(
             target,
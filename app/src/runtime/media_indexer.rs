@@ -0,0 +1,85 @@
+use crate::app::actions::AppActions;
+use crate::directories::media_index_file_path;
+use crate::runtime::task::TaskHandler;
+use crate::runtime::RuntimeSystem;
+use apodex::media::heed::HeedMediaCache;
+use apodex::media::index::{IndexUpdateReport, MediaIndex, MediaIndexError};
+use egui::Context;
+use tokio::runtime::Handle;
+
+/// Most bits two perceptual hashes may differ in for the images to count as the same
+pub const DEFAULT_MAX_DISTANCE: u32 = 6;
+
+/// Keeps the media index of the cached images, updated on demand
+pub struct MediaIndexer {
+    index: MediaIndex,
+    /// Changes with every update of the index, to invalidate results derived from it
+    generation: u64,
+    task: TaskHandler<Result<(IndexUpdateReport, MediaIndex), MediaIndexError>>,
+}
+
+impl Default for MediaIndexer {
+    fn default() -> Self {
+        Self {
+            // Rebuilt from the cache if missing or written by an incompatible version
+            index: MediaIndex::load(media_index_file_path()).unwrap_or_default(),
+            generation: 0,
+            task: Default::default(),
+        }
+    }
+}
+
+impl MediaIndexer {
+    pub fn index(&self) -> &MediaIndex {
+        &self.index
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.task.is_busy()
+    }
+
+    pub fn status(&self) -> Option<String> {
+        self.task.status()
+    }
+
+    /// Indexes newly cached images in the background and saves the index
+    pub fn start_update(&mut self, handle: &Handle, cache: HeedMediaCache) {
+        let mut index = self.index.clone();
+        self.task.spawn(handle, move |ctx| async move {
+            ctx.set_status("Reading cache");
+            let report = index.update(&cache, |done, total| {
+                ctx.set_status(format!("Indexing images {done}/{total}"));
+            })?;
+            index.save(media_index_file_path())?;
+            Ok((report, index))
+        });
+    }
+}
+
+impl RuntimeSystem for MediaIndexer {
+    fn update(&mut self, _ctx: &Context, _handle: &Handle, actions: &AppActions) {
+        match self.task.poll() {
+            Some(Ok((report, index))) => {
+                self.index = index;
+                self.generation += 1;
+                actions.toast_success(format!(
+                    "Indexed {} new images, {} in total",
+                    report.indexed,
+                    self.index.len()
+                ));
+                if report.unreadable > 0 {
+                    actions.toast_warning(format!(
+                        "Skipped {} images that could not be read, verify the cache to find them",
+                        report.unreadable
+                    ));
+                }
+            }
+            Some(Err(err)) => actions.toast_error(format!("Indexing media failed: {err}")),
+            None => {}
+        }
+    }
+}
@@ -67,7 +67,12 @@ impl RuntimeSystem for Scraper {
                 Some(Ok((date, page))) => {
                     if let Some(html) = page {
                         actions.insert_html(date, html);
+                    } else if date < ApodDate::publication_today() {
+                        actions.record_missing_date(date);
+                        actions
+                            .toast_warning(format!("Page for {date} not found, marked as missing"))
                     } else {
+                        // Might just not be published yet
                         actions.toast_warning(format!("Page for {date} not found"))
                     }
                 }
@@ -1,4 +1,8 @@
+pub mod animation_player;
 pub mod apod_table;
+pub mod date_range_select;
 pub mod enum_select;
+pub mod gallery;
+pub mod on_this_day;
 pub mod option_enum_select;
 pub mod toggle_button;
@@ -0,0 +1,144 @@
+use crate::runtime::animation::{Animation, MediaKey};
+use crate::runtime::apod_media::fit_to_bounds;
+use egui::{Response, Ui, Widget};
+use egui_phosphor::regular::{PAUSE, PLAY, SKIP_BACK, SKIP_FORWARD};
+
+/// Position in the shown animation, starts over when another animation is shown
+pub struct AnimationPlayback {
+    key: Option<MediaKey>,
+    playing: bool,
+    frame: usize,
+    /// Input time in seconds the current frame was shown at
+    frame_shown_at: f64,
+}
+
+impl Default for AnimationPlayback {
+    fn default() -> Self {
+        Self {
+            key: None,
+            playing: true,
+            frame: 0,
+            frame_shown_at: 0.0,
+        }
+    }
+}
+
+impl AnimationPlayback {
+    /// Moves to the frame due at `now`, frames are skipped rather than played late
+    fn advance(&mut self, animation: &Animation, now: f64) {
+        let delay = animation.frames[self.frame].delay.as_secs_f64();
+        let elapsed = now - self.frame_shown_at;
+        if elapsed < delay {
+            return;
+        }
+
+        self.frame = (self.frame + 1) % animation.frames.len();
+        // After a pause or while the window was hidden there is nothing to catch up on
+        self.frame_shown_at = if elapsed < 2.0 * delay {
+            self.frame_shown_at + delay
+        } else {
+            now
+        };
+    }
+
+    fn step(&mut self, animation: &Animation, forward: bool, now: f64) {
+        let count = animation.frames.len();
+        self.frame = if forward {
+            (self.frame + 1) % count
+        } else {
+            (self.frame + count - 1) % count
+        };
+        self.playing = false;
+        self.frame_shown_at = now;
+    }
+}
+
+/// The current frame of an animation with play, pause and step controls above it
+pub struct AnimationPlayer<'a> {
+    playback: &'a mut AnimationPlayback,
+    key: MediaKey,
+    animation: &'a Animation,
+}
+
+impl<'a> AnimationPlayer<'a> {
+    pub fn new(
+        playback: &'a mut AnimationPlayback,
+        key: MediaKey,
+        animation: &'a Animation,
+    ) -> Self {
+        Self {
+            playback,
+            key,
+            animation,
+        }
+    }
+
+    fn render_controls(&mut self, ui: &mut Ui, now: f64) {
+        let count = self.animation.frames.len();
+        ui.horizontal(|ui| {
+            if ui
+                .button(SKIP_BACK)
+                .on_hover_text("Previous frame")
+                .clicked()
+            {
+                self.playback.step(self.animation, false, now);
+            }
+
+            let (icon, hover) = if self.playback.playing {
+                (PAUSE, "Pause")
+            } else {
+                (PLAY, "Play")
+            };
+            if ui.button(icon).on_hover_text(hover).clicked() {
+                self.playback.playing = !self.playback.playing;
+                self.playback.frame_shown_at = now;
+            }
+
+            if ui
+                .button(SKIP_FORWARD)
+                .on_hover_text("Next frame")
+                .clicked()
+            {
+                self.playback.step(self.animation, true, now);
+            }
+
+            ui.label(format!("Frame {}/{}", self.playback.frame + 1, count));
+            if self.animation.truncated {
+                ui.small(format!("Only the first {count} frames are shown"))
+                    .on_hover_text(
+                        "The remaining frames don't fit into memory or can't be decoded",
+                    );
+            }
+        });
+    }
+}
+
+impl Widget for AnimationPlayer<'_> {
+    fn ui(mut self, ui: &mut Ui) -> Response {
+        let now = ui.input(|input| input.time);
+        if self.playback.key != Some(self.key) {
+            *self.playback = AnimationPlayback {
+                key: Some(self.key),
+                frame_shown_at: now,
+                ..Default::default()
+            };
+        }
+        self.playback.frame = self.playback.frame.min(self.animation.frames.len() - 1);
+
+        if self.playback.playing {
+            self.playback.advance(self.animation, now);
+            let frame = &self.animation.frames[self.playback.frame];
+            let remaining = frame.delay.as_secs_f64() - (now - self.playback.frame_shown_at);
+            ui.ctx()
+                .request_repaint_after_secs(remaining.max(0.0) as f32);
+        }
+
+        ui.vertical(|ui| {
+            self.render_controls(ui, now);
+            let texture = &self.animation.frames[self.playback.frame].texture;
+            let size = fit_to_bounds(ui.available_size(), self.animation.aspect_ratio());
+            ui.image((texture.id(), size));
+        })
+        .response
+    }
+}
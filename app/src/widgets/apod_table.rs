@@ -1,10 +1,14 @@
 use crate::app::actions::AppActions;
 use crate::runtime::apod_data::ApodData;
+use crate::runtime::media_indexer::MediaIndexer;
 use crate::widgets::option_enum_select::OptionEnumSelect;
 use crate::windows::WindowId;
 use apodex::date::ApodDate;
-use egui::{CursorIcon, Hyperlink, Popup, RectAlign, Response, RichText, Ui, Widget};
+use apodex::media::MediaVariant;
+use egui::{CursorIcon, Hyperlink, Popup, RectAlign, Response, RichText, Slider, Ui, Widget};
 use egui_extras::{Column, TableBuilder};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::time::Instant;
 use strum_macros::EnumIter;
@@ -16,8 +20,13 @@ pub struct ApodTable<'a> {
 }
 
 impl<'a> ApodTable<'a> {
-    pub fn new(state: &'a mut ApodTableState, actions: &'a AppActions, data: &'a ApodData) -> Self {
-        state.sort(data);
+    pub fn new(
+        state: &'a mut ApodTableState,
+        actions: &'a AppActions,
+        data: &'a ApodData,
+        indexer: &MediaIndexer,
+    ) -> Self {
+        state.sort(data, indexer);
         Self {
             state,
             actions,
@@ -36,7 +45,7 @@ impl Widget for ApodTable<'_> {
                     ui.label(format!(
                         "Filtered Entries: {}/{}",
                         self.state.entry_count(),
-                        ApodDate::total_apod_days()
+                        self.data.total_apod_days()
                     ))
                 });
             });
@@ -54,6 +63,17 @@ impl Widget for ApodTable<'_> {
                             self.state.sort_clean = false;
                         };
                     });
+                    let mut media_filter = self.state.media_filter;
+                    ui.horizontal(|ui| {
+                        ui.label("Media:");
+                        OptionEnumSelect::new(&mut media_filter, "media_filter_select").ui(ui);
+                    })
+                    .response
+                    .on_hover_text("Needs the media index, update it in the duplicates window");
+                    if self.state.media_filter != media_filter {
+                        self.state.media_filter = media_filter;
+                        self.state.sort_clean = false;
+                    }
                     ui.checkbox(&mut self.state.show_media_url, "Show media URL");
                 });
             });
@@ -185,10 +205,13 @@ impl Widget for ApodTable<'_> {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// Sort keys, the image based ones need the media index and sort entries without it last
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, serde::Serialize, serde::Deserialize)]
 pub enum ApodTableColumn {
     Date,
     Title,
+    Hue,
+    Brightness,
 }
 
 impl Display for ApodTableColumn {
@@ -196,6 +219,8 @@ impl Display for ApodTableColumn {
         match self {
             ApodTableColumn::Date => write!(f, "Date"),
             ApodTableColumn::Title => write!(f, "Title"),
+            ApodTableColumn::Hue => write!(f, "Hue"),
+            ApodTableColumn::Brightness => write!(f, "Brightness"),
         }
     }
 }
@@ -219,6 +244,24 @@ impl Display for StatusFilter {
     }
 }
 
+/// Filters by the metadata of the cached image
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, serde::Serialize, serde::Deserialize)]
+pub enum MediaFilter {
+    LargerThan4k,
+    Animated,
+    HasExif,
+}
+
+impl Display for MediaFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaFilter::LargerThan4k => write!(f, "HD larger than 4K"),
+            MediaFilter::Animated => write!(f, "Animated"),
+            MediaFilter::HasExif => write!(f, "Has EXIF"),
+        }
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct ApodTableState {
     sort_column: Option<ApodTableColumn>,
@@ -228,6 +271,15 @@ pub struct ApodTableState {
     explanation_filter: String,
     selected_date: Option<ApodDate>,
     show_media_url: bool,
+    #[serde(default)]
+    media_filter: Option<MediaFilter>,
+    #[serde(default)]
+    color_search: Option<[u8; 3]>,
+    /// The picked color, kept while the search is disabled
+    #[serde(default = "default_color_search")]
+    color_search_last: [u8; 3],
+    #[serde(default = "default_color_tolerance")]
+    color_tolerance: f32,
     #[serde(default, skip)]
     status_filter_popup_open: bool,
     #[serde(default, skip)]
@@ -238,11 +290,16 @@ pub struct ApodTableState {
     data_last_update: Instant,
     #[serde(default, skip)]
     sort_clean: bool,
+    /// Generation of the media index the media filter was applied with
+    #[serde(default, skip)]
+    index_generation: u64,
 }
 
 impl ApodTableState {
-    pub fn sort(&mut self, data: &ApodData) {
-        if self.data_last_update <= data.last_update() {
+    pub fn sort(&mut self, data: &ApodData, indexer: &MediaIndexer) {
+        if self.data_last_update <= data.last_update()
+            || (self.uses_index() && self.index_generation != indexer.generation())
+        {
             self.sort_clean = false;
         }
 
@@ -252,7 +309,7 @@ impl ApodTableState {
             self.sort_clean = true;
         }
 
-        self.cached_sorted_dates = ApodDate::iter_till_today().collect();
+        self.cached_sorted_dates = data.iter_till_today().collect();
 
         if let Some(column) = self.sort_column {
             match column {
@@ -284,9 +341,47 @@ impl ApodTableState {
                         }
                     });
                 }
+                ApodTableColumn::Hue | ApodTableColumn::Brightness => {
+                    let index = indexer.index();
+                    let key = |date: &ApodDate| {
+                        let palette = &index.get(*date)?.palette;
+                        match column {
+                            ApodTableColumn::Hue => palette.hue(),
+                            _ => Some(palette.brightness),
+                        }
+                    };
+                    // Entries without a value belong at the end in both directions
+                    self.cached_sorted_dates
+                        .sort_by(|a, b| match (key(a), key(b)) {
+                            (Some(a), Some(b)) => {
+                                let ord = a.total_cmp(&b);
+                                if self.sort_ascending {
+                                    ord
+                                } else {
+                                    ord.reverse()
+                                }
+                            }
+                            (Some(_), None) => Ordering::Less,
+                            (None, Some(_)) => Ordering::Greater,
+                            (None, None) => Ordering::Equal,
+                        });
+                }
             }
         }
 
+        if let Some(rgb) = self.color_search {
+            let closest = indexer.index().closest_to_color(rgb, self.color_tolerance);
+            let order: HashMap<ApodDate, usize> = closest
+                .iter()
+                .enumerate()
+                .map(|(rank, (date, _))| (*date, rank))
+                .collect();
+            self.cached_sorted_dates
+                .retain(|date| order.contains_key(date));
+            self.cached_sorted_dates
+                .sort_by_key(|date| order.get(date).copied());
+        }
+
         if let Some(filter) = &self.status_filter {
             self.cached_sorted_dates.retain(|date| match filter {
                 StatusFilter::Ok => {
@@ -316,9 +411,75 @@ impl ApodTableState {
             });
         }
 
+        if let Some(filter) = self.media_filter {
+            let index = indexer.index();
+            self.cached_sorted_dates.retain(|date| {
+                index.metadata(*date).is_some_and(|metadata| match filter {
+                    MediaFilter::LargerThan4k => {
+                        metadata.variant == MediaVariant::Hd && metadata.is_larger_than_4k()
+                    }
+                    MediaFilter::Animated => metadata.is_animated(),
+                    MediaFilter::HasExif => metadata.exif.is_some(),
+                })
+            });
+        }
+        self.index_generation = indexer.generation();
+
         self.data_last_update = Instant::now();
     }
 
+    fn uses_index(&self) -> bool {
+        self.media_filter.is_some()
+            || self.color_search.is_some()
+            || matches!(
+                self.sort_column,
+                Some(ApodTableColumn::Hue | ApodTableColumn::Brightness)
+            )
+    }
+
+    /// Sorts by an image based key, the gallery offers those since the table has no columns for them
+    pub fn render_image_sort(&mut self, ui: &mut Ui) {
+        let mut sort_column = self.sort_column;
+        OptionEnumSelect::new(&mut sort_column, "image_sort_select")
+            .label("Sort")
+            .ui(ui);
+        if ui
+            .button(self.sort_arrow())
+            .on_hover_text("Reverse order")
+            .clicked()
+        {
+            self.sort_ascending = !self.sort_ascending;
+            self.sort_clean = false;
+        }
+        if self.sort_column != sort_column {
+            self.sort_column = sort_column;
+            self.sort_clean = false;
+        }
+    }
+
+    /// Only shows entries with a palette color close to the picked one, the closest first
+    pub fn render_color_search(&mut self, ui: &mut Ui) {
+        let mut enabled = self.color_search.is_some();
+        let mut rgb = self.color_search.unwrap_or(self.color_search_last);
+        let mut tolerance = self.color_tolerance;
+
+        ui.checkbox(&mut enabled, "Color")
+            .on_hover_text("Needs the media index, update it in the duplicates window");
+        ui.color_edit_button_srgb(&mut rgb);
+        ui.add_enabled(
+            enabled,
+            Slider::new(&mut tolerance, 0.05..=0.6).text("Tolerance"),
+        );
+
+        let color_search = enabled.then_some(rgb);
+        if self.color_search != color_search || self.color_tolerance != tolerance {
+            self.color_search = color_search;
+            self.color_search_last = rgb;
+            self.color_tolerance = tolerance;
+            self.sort_clean = false;
+        }
+    }
+
     pub fn entry_count(&self) -> usize {
         self.cached_sorted_dates.len()
     }
@@ -327,6 +488,14 @@ impl ApodTableState {
         self.cached_sorted_dates.get(index).copied()
     }
 
+    pub fn selected_date(&self) -> Option<ApodDate> {
+        self.selected_date
+    }
+
+    pub fn set_selected_date(&mut self, date: Option<ApodDate>) {
+        self.selected_date = date;
+    }
+
     pub fn sort_arrow(&self) -> &'static str {
         if self.sort_ascending {
             egui_phosphor::regular::SORT_ASCENDING
@@ -483,12 +652,25 @@ impl Default for ApodTableState {
             status_filter: None,
             status_filter_popup_open: false,
             show_media_url: false,
+            media_filter: None,
+            color_search: None,
+            color_search_last: default_color_search(),
+            color_tolerance: default_color_tolerance(),
             title_filter: String::new(),
             explanation_filter: String::new(),
             selected_date: None,
             title_filter_popup_open: false,
-            cached_sorted_dates: ApodDate::iter_till_today().collect(),
+            cached_sorted_dates: Vec::new(),
             sort_clean: false,
+            index_generation: 0,
         }
     }
 }
+
+fn default_color_search() -> [u8; 3] {
+    [40, 80, 200]
+}
+
+fn default_color_tolerance() -> f32 {
+    0.25
+}
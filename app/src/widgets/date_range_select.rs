@@ -0,0 +1,52 @@
+use apodex::date::ApodDate;
+use chrono::NaiveDate;
+use egui::{Ui, Widget};
+use egui_extras::DatePickerButton;
+
+/// Two date pickers limited to APOD dates, the end is kept after the start
+pub struct DateRangeSelect<'a> {
+    start: &'a mut ApodDate,
+    end: &'a mut ApodDate,
+    id: &'a str,
+}
+
+impl<'a> DateRangeSelect<'a> {
+    pub fn new(start: &'a mut ApodDate, end: &'a mut ApodDate, id: &'a str) -> Self {
+        Self { start, end, id }
+    }
+}
+
+impl Widget for DateRangeSelect<'_> {
+    fn ui(self, ui: &mut Ui) -> egui::Response {
+        // The later of both current dates, so the year is selectable in either mode
+        let latest = ApodDate::today().max(ApodDate::publication_today());
+        let years = ApodDate::START.year()..=latest.year();
+        let start_id = format!("{}_start", self.id);
+        let end_id = format!("{}_end", self.id);
+
+        let mut start = NaiveDate::from(*self.start);
+        let mut end = NaiveDate::from(*self.end);
+
+        let response = ui
+            .horizontal(|ui| {
+                let start_response = ui.add(
+                    DatePickerButton::new(&mut start)
+                        .id_salt(&start_id)
+                        .start_end_years(years.clone()),
+                );
+                ui.label("to");
+                let end_response = ui.add(
+                    DatePickerButton::new(&mut end)
+                        .id_salt(&end_id)
+                        .start_end_years(years),
+                );
+                start_response | end_response
+            })
+            .inner;
+
+        *self.start = ApodDate::from(start).max(ApodDate::START);
+        *self.end = ApodDate::from(end).max(*self.start);
+
+        response
+    }
+}
@@ -0,0 +1,181 @@
+use crate::app::actions::AppActions;
+use crate::runtime::Runtime;
+use crate::widgets::apod_table::ApodTableState;
+use crate::windows::WindowId;
+use apodex::date::ApodDate;
+use egui::{
+    Align2, Color32, CursorIcon, FontId, Rect, Response, ScrollArea, Sense, Slider, Stroke,
+    StrokeKind, Ui, Vec2, Widget,
+};
+
+const SPACING: f32 = 6.0;
+
+/// Thumbnails of the entries in the order and with the filters of the data table
+pub struct Gallery<'a> {
+    state: &'a mut GalleryState,
+    table_state: &'a mut ApodTableState,
+    actions: &'a AppActions,
+    runtime: &'a mut Runtime,
+}
+
+impl<'a> Gallery<'a> {
+    pub fn new(
+        state: &'a mut GalleryState,
+        table_state: &'a mut ApodTableState,
+        actions: &'a AppActions,
+        runtime: &'a mut Runtime,
+    ) -> Self {
+        table_state.sort(&runtime.data, &runtime.indexer);
+        Self {
+            state,
+            table_state,
+            actions,
+            runtime,
+        }
+    }
+
+    fn render_cell(&mut self, ui: &mut Ui, date: ApodDate) {
+        let size = self.state.thumbnail_size;
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(size), Sense::click());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+
+        let entry = self.runtime.data.get_entry(date);
+        let title = entry.map(|entry| entry.title.clone());
+        let kind = entry.and_then(|entry| entry.media.kind());
+        let is_image = kind.is_some_and(|kind| kind.is_image());
+        // Thumbnails of embedded videos are generated from their poster once it was fetched
+        let has_poster = kind.is_some_and(|kind| kind.is_embedded_video());
+
+        let thumbnail = if is_image || has_poster {
+            self.runtime
+                .media
+                .get_thumbnail(date, size * ui.ctx().pixels_per_point())
+        } else {
+            None
+        };
+
+        if let Some((id, aspect)) = thumbnail {
+            let image_rect = Rect::from_center_size(rect.center(), fit_to_square(size, aspect));
+            egui::Image::new((id, image_rect.size())).paint_at(ui, image_rect);
+        } else {
+            let icon = if entry.is_none() {
+                egui_phosphor::regular::QUESTION
+            } else if !is_image {
+                egui_phosphor::regular::FILM_STRIP
+            } else if self.runtime.media.is_thumbnail_unavailable(date) {
+                egui_phosphor::regular::IMAGE
+            } else {
+                egui_phosphor::regular::HOURGLASS
+            };
+            painter.text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                icon,
+                FontId::proportional(size / 4.0),
+                ui.visuals().weak_text_color(),
+            );
+        }
+
+        if self.table_state.selected_date() == Some(date) {
+            painter.rect_stroke(
+                rect,
+                4.0,
+                Stroke::new(3.0, ui.visuals().selection.stroke.color),
+                StrokeKind::Inside,
+            );
+        } else if response.hovered() {
+            painter.rect_stroke(
+                rect,
+                4.0,
+                Stroke::new(1.0, Color32::GRAY),
+                StrokeKind::Inside,
+            );
+        }
+
+        let hover_text = match title {
+            Some(title) => format!("{date}\n{title}"),
+            None => format!("{date}\nMissing"),
+        };
+        let response = response
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .on_hover_text(hover_text);
+
+        if response.clicked() {
+            self.table_state.set_selected_date(Some(date));
+            self.actions.details_select_date(date);
+            self.actions.open_and_focus_window(WindowId::Details);
+        }
+    }
+}
+
+impl Widget for Gallery<'_> {
+    fn ui(mut self, ui: &mut Ui) -> Response {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Filtered Entries: {}/{}",
+                    self.table_state.entry_count(),
+                    self.runtime.data.total_apod_days()
+                ));
+                ui.separator();
+                ui.add(
+                    Slider::new(&mut self.state.thumbnail_size, 64.0..=320.0)
+                        .text("Size")
+                        .suffix(" px"),
+                );
+                ui.separator();
+                self.table_state.render_image_sort(ui);
+                ui.separator();
+                self.table_state.render_color_search(ui);
+            });
+
+            ui.separator();
+
+            let size = self.state.thumbnail_size;
+            let columns = ((ui.available_width() + SPACING) / (size + SPACING))
+                .floor()
+                .max(1.0) as usize;
+            let rows = self.table_state.entry_count().div_ceil(columns);
+
+            ui.spacing_mut().item_spacing = Vec2::splat(SPACING);
+            ScrollArea::vertical()
+                .auto_shrink(false)
+                .show_rows(ui, size, rows, |ui, row_range| {
+                    for row in row_range {
+                        ui.horizontal(|ui| {
+                            for column in 0..columns {
+                                let Some(date) = self.table_state.get_date(row * columns + column)
+                                else {
+                                    break;
+                                };
+                                self.render_cell(ui, date);
+                            }
+                        });
+                    }
+                });
+        })
+        .response
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct GalleryState {
+    pub thumbnail_size: f32,
+}
+
+impl Default for GalleryState {
+    fn default() -> Self {
+        Self {
+            thumbnail_size: 160.0,
+        }
+    }
+}
+
+fn fit_to_square(size: f32, aspect: f32) -> Vec2 {
+    if aspect >= 1.0 {
+        Vec2::new(size, size / aspect)
+    } else {
+        Vec2::new(size * aspect, size)
+    }
+}
@@ -0,0 +1,163 @@
+use crate::app::actions::AppActions;
+use crate::runtime::apod_media::fit_to_bounds;
+use crate::runtime::Runtime;
+use crate::windows::WindowId;
+use apodex::date::ApodDate;
+use apodex::ApodEntry;
+use egui::{Button, CursorIcon, Frame, Label, Response, ScrollArea, Sense, Ui, Vec2, Widget};
+
+const CARD_WIDTH: f32 = 160.0;
+
+/// The entries of one day across all years of APOD, as a horizontal strip of cards
+pub struct OnThisDay<'a> {
+    state: &'a mut OnThisDayState,
+    actions: &'a AppActions,
+    runtime: &'a mut Runtime,
+}
+
+impl<'a> OnThisDay<'a> {
+    pub fn new(
+        state: &'a mut OnThisDayState,
+        actions: &'a AppActions,
+        runtime: &'a mut Runtime,
+    ) -> Self {
+        Self {
+            state,
+            actions,
+            runtime,
+        }
+    }
+
+    fn render_navigation(&mut self, ui: &mut Ui, day: ApodDate, count: usize) {
+        ui.horizontal(|ui| {
+            if ui
+                .button(egui_phosphor::regular::CARET_LEFT)
+                .on_hover_text("Previous day")
+                .clicked()
+            {
+                self.state.day_offset -= 1;
+            }
+
+            ui.heading(format!("On this day: {}", day.format("%B %-d")));
+
+            if ui
+                .button(egui_phosphor::regular::CARET_RIGHT)
+                .on_hover_text("Next day")
+                .clicked()
+            {
+                self.state.day_offset += 1;
+            }
+
+            if ui
+                .add_enabled(self.state.day_offset != 0, Button::new("Today"))
+                .clicked()
+            {
+                self.state.day_offset = 0;
+            }
+
+            ui.separator();
+            ui.label(format!("{count} entries"));
+        });
+    }
+
+    fn render_card(&mut self, ui: &mut Ui, entry: &ApodEntry) {
+        let response = Frame::group(ui.style())
+            .show(ui, |ui| {
+                ui.set_width(CARD_WIDTH);
+                ui.vertical_centered(|ui| {
+                    ui.strong(entry.date.year().to_string());
+
+                    let thumbnail_size = Vec2::splat(CARD_WIDTH);
+                    let is_image = entry.media.kind().is_some_and(|kind| kind.is_image());
+                    let has_poster = entry
+                        .media
+                        .kind()
+                        .is_some_and(|kind| kind.is_embedded_video());
+                    // Like the gallery only cached media is shown, nothing is fetched for the strip
+                    let texture = if is_image || has_poster {
+                        let pixels = CARD_WIDTH * ui.ctx().pixels_per_point();
+                        self.runtime.media.get_thumbnail(entry.date, pixels)
+                    } else {
+                        None
+                    };
+
+                    match texture {
+                        Some((id, aspect)) => {
+                            let size = fit_to_bounds(thumbnail_size, aspect);
+                            ui.add_sized(thumbnail_size, egui::Image::new((id, size)));
+                        }
+                        None if is_image
+                            && self.runtime.media.is_thumbnail_unavailable(entry.date) =>
+                        {
+                            ui.add_sized(thumbnail_size, Label::new(egui_phosphor::regular::IMAGE))
+                                .on_hover_text("Not cached yet, open the details to download it");
+                        }
+                        None if is_image => {
+                            ui.add_sized(thumbnail_size, egui::Spinner::new());
+                        }
+                        None => {
+                            ui.add_sized(
+                                thumbnail_size,
+                                Label::new(egui_phosphor::regular::FILM_STRIP),
+                            );
+                        }
+                    }
+
+                    ui.add(Label::new(&entry.title).truncate());
+                });
+            })
+            .response
+            .interact(Sense::click())
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .on_hover_text(&entry.title);
+
+        if response.clicked() {
+            self.actions.details_select_date(entry.date);
+            self.actions.open_and_focus_window(WindowId::Details);
+        }
+    }
+}
+
+impl Widget for OnThisDay<'_> {
+    fn ui(mut self, ui: &mut Ui) -> Response {
+        let day = self
+            .runtime
+            .data
+            .current_date()
+            .add_days(self.state.day_offset);
+        let entries: Vec<ApodEntry> = self
+            .runtime
+            .data
+            .entries_on_this_day(day.month(), day.day())
+            .into_iter()
+            .rev()
+            .cloned()
+            .collect();
+
+        ui.vertical(|ui| {
+            self.render_navigation(ui, day, entries.len());
+            ui.separator();
+
+            if entries.is_empty() {
+                ui.small("No entries for this day.");
+                return;
+            }
+
+            ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for entry in &entries {
+                        self.render_card(ui, entry);
+                    }
+                });
+            });
+        })
+        .response
+    }
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+pub struct OnThisDayState {
+    /// Days away from the current date, always starts at today
+    #[serde(skip)]
+    pub day_offset: i32,
+}
@@ -1,23 +1,34 @@
 use crate::app::ApodexApp;
 use crate::widgets::toggle_button::ToggleButton;
+use crate::windows::cache::CacheWindow;
 use crate::windows::data::DataWindow;
 use crate::windows::details::DetailsWindow;
+use crate::windows::duplicates::DuplicatesWindow;
 use crate::windows::export::ExportWindow;
 use crate::windows::import::ImportWindow;
 use crate::windows::scrape::ScrapeWindow;
 use egui::{Context, Ui, Widget, WidgetText};
 use serde::{Deserialize, Serialize};
 
+mod cache;
 mod data;
 mod details;
+mod duplicates;
 mod export;
 mod import;
 mod scrape;
 
+pub use export::EntryExport;
+pub use import::EntryImport;
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct WindowState {
+    #[serde(default)]
+    pub cache: cache::CacheWindowState,
     pub data: data::DataWindowState,
     pub details: details::DetailsWindowState,
+    #[serde(default)]
+    pub duplicates: duplicates::DuplicatesWindowState,
     pub export: export::ExportWindowState,
     pub import: import::ImportWindowState,
     pub scrape: scrape::ScrapeWindowState,
@@ -25,17 +36,27 @@ pub struct WindowState {
 
 impl WindowState {
     pub fn update(&mut self, ctx: &Context, app: &mut ApodexApp) {
-        DataWindow::new(&mut self.data, &app.actions, &app.runtime.data).show(ctx);
+        CacheWindow::new(&mut self.cache, &mut app.runtime).show(ctx);
+        DataWindow::new(
+            &mut self.data,
+            &app.actions,
+            &app.runtime.data,
+            &app.runtime.indexer,
+        )
+        .show(ctx);
         DetailsWindow::new(&mut self.details, &mut app.runtime).show(ctx);
+        DuplicatesWindow::new(&mut self.duplicates, &app.actions, &mut app.runtime).show(ctx);
         ExportWindow::new(&mut self.export, &mut app.runtime).show(ctx);
         ImportWindow::new(&mut self.import, &mut app.runtime).show(ctx);
-        ScrapeWindow::new(&mut self.scrape, &mut app.runtime).show(ctx);
+        ScrapeWindow::new(&mut self.scrape, &app.actions, &mut app.runtime).show(ctx);
     }
 
     pub fn open_and_focus(&mut self, ctx: &Context, window_id: WindowId) {
         match window_id {
+            WindowId::Cache => self.cache.set_open(true),
             WindowId::Data => self.data.set_open(true),
             WindowId::Details => self.details.set_open(true),
+            WindowId::Duplicates => self.duplicates.set_open(true),
             WindowId::Export => self.export.set_open(true),
             WindowId::Import => self.import.set_open(true),
             WindowId::Scrape => self.scrape.set_open(true),
@@ -46,8 +67,10 @@ impl WindowState {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum WindowId {
+    Cache,
     Data,
     Details,
+    Duplicates,
     Export,
     Import,
     Scrape,
@@ -0,0 +1,471 @@
+use crate::runtime::apod_media::{CacheOperation, CacheUsage};
+use crate::runtime::Runtime;
+use crate::widgets::date_range_select::DateRangeSelect;
+use crate::windows::{AppWindow, ToggleableWindowState, WindowId};
+use apodex::date::range::ApodDateRange;
+use apodex::date::{ApodDate, CurrentDateMode};
+use apodex::media::download::DownloadOptions;
+use apodex::media::{EvictionPolicy, MediaVariant};
+use chrono::Utc;
+use egui::{Button, Checkbox, DragValue, Grid, ProgressBar, ScrollArea, Ui, WidgetText};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub struct CacheWindow<'a> {
+    state: &'a mut CacheWindowState,
+    runtime: &'a mut Runtime,
+}
+
+impl<'a> CacheWindow<'a> {
+    pub fn new(state: &'a mut CacheWindowState, runtime: &'a mut Runtime) -> Self {
+        Self { state, runtime }
+    }
+
+    fn render_usage(ui: &mut Ui, usage: &CacheUsage) {
+        Grid::new("cache_usage_grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Files");
+                ui.label(usage.stats.count.to_string());
+                ui.end_row();
+
+                ui.label("Media");
+                ui.label(format_bytes(usage.stats.total_bytes));
+                ui.end_row();
+
+                ui.label("In use")
+                    .on_hover_text("Including database overhead, without freed pages");
+                ui.label(format_bytes(usage.used_size));
+                ui.end_row();
+
+                ui.label("Database file")
+                    .on_hover_text("The file never shrinks, freed pages are reused");
+                ui.label(format_bytes(usage.disk_size));
+                ui.end_row();
+
+                ui.label("Capacity");
+                ui.label(format!(
+                    "{} of at most {}",
+                    format_bytes(usage.map_size as u64),
+                    format_bytes(usage.max_size_mb as u64 * 1024 * 1024)
+                ));
+                ui.end_row();
+            });
+
+        if usage.stats.by_variant.is_empty() {
+            return;
+        }
+
+        ui.separator();
+        Grid::new("cache_variant_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Variant");
+                ui.strong("Files");
+                ui.strong("Size");
+                ui.end_row();
+
+                for (variant, (count, bytes)) in &usage.stats.by_variant {
+                    ui.label(variant.to_string());
+                    ui.label(count.to_string());
+                    ui.label(format_bytes(*bytes));
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn render_eviction(&mut self, ui: &mut Ui, enabled: bool) {
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.state.max_size_mb)
+                    .range(0..=1_000_000)
+                    .suffix(" MB"),
+            );
+            if ui
+                .add_enabled(enabled, Button::new("Shrink"))
+                .on_hover_text("Removes the least recently viewed media until the cache fits")
+                .clicked()
+            {
+                let max_bytes = self.state.max_size_mb as u64 * 1024 * 1024;
+                self.run(CacheOperation::Evict(EvictionPolicy::MaxBytes(max_bytes)));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                DragValue::new(&mut self.state.unused_days)
+                    .range(1..=3650)
+                    .suffix(" days"),
+            );
+            if ui
+                .add_enabled(enabled, Button::new("Remove unused"))
+                .on_hover_text("Removes media not viewed for this many days")
+                .clicked()
+            {
+                let timestamp =
+                    Utc::now().timestamp() - self.state.unused_days as i64 * SECONDS_PER_DAY;
+                self.run(CacheOperation::Evict(EvictionPolicy::NotAccessedSince(
+                    timestamp,
+                )));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(DateRangeSelect::new(
+                &mut self.state.range_start,
+                &mut self.state.range_end,
+                "cache_range",
+            ));
+            if ui
+                .add_enabled(enabled, Button::new("Remove range"))
+                .clicked()
+            {
+                let range = ApodDateRange::new(self.state.range_start, self.state.range_end);
+                self.run(CacheOperation::Evict(EvictionPolicy::DateRange(range)));
+            }
+        });
+
+        ui.separator();
+
+        if self.state.confirm_clear {
+            ui.horizontal(|ui| {
+                ui.label("Remove all cached media and thumbnails?");
+                if ui.add_enabled(enabled, Button::new("Clear")).clicked() {
+                    self.state.confirm_clear = false;
+                    self.run(CacheOperation::Clear);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.state.confirm_clear = false;
+                }
+            });
+        } else if ui
+            .add_enabled(enabled, Button::new("Clear cache"))
+            .on_hover_text("Removes all cached media and thumbnails")
+            .clicked()
+        {
+            self.state.confirm_clear = true;
+        }
+    }
+
+    fn render_verify(&mut self, ui: &mut Ui, enabled: bool) {
+        // A running download would overwrite the requeued media in its state file
+        let download_busy = self.runtime.media.download_busy();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.state.verify_delete, "Delete corrupt");
+            ui.add_enabled(
+                !download_busy,
+                Checkbox::new(&mut self.state.verify_redownload, "Download again"),
+            )
+            .on_hover_text("Adds corrupt media to the saved download, to be resumed below");
+        });
+
+        if ui
+            .add_enabled(enabled, Button::new("Verify"))
+            .on_hover_text("Checks all cached media against its checksum and decodes every image")
+            .clicked()
+        {
+            self.run(CacheOperation::Verify {
+                delete_corrupt: self.state.verify_delete,
+                redownload: self.state.verify_redownload && !download_busy,
+            });
+        }
+
+        let Some(report) = self.runtime.media.verify_report() else {
+            return;
+        };
+        ui.small(format!(
+            "{} checked, {} corrupt",
+            report.checked,
+            report.corrupt.len()
+        ));
+        if report.is_ok() {
+            return;
+        }
+        ScrollArea::vertical()
+            .id_salt("cache_verify_report")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                Grid::new("cache_verify_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for corrupt in &report.corrupt {
+                            ui.label(corrupt.date.to_string());
+                            ui.label(corrupt.variant.to_string());
+                            ui.label(corrupt.problem.to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    fn render_download(&mut self, ui: &mut Ui) {
+        if self.runtime.media.download_busy() {
+            let progress = self.runtime.media.download_progress();
+            let eta = progress
+                .eta
+                .map(|eta| format!(", ETA {}", humantime::format_duration(round_secs(eta))))
+                .unwrap_or_default();
+            let status = self.runtime.media.download_status().unwrap_or_default();
+            ui.add(ProgressBar::new(progress.fraction()).text(format!(
+                "{status}: {}/{}, {}{eta}",
+                progress.completed + progress.failed,
+                progress.total(),
+                format_bytes(progress.bytes)
+            )));
+            if progress.failed > 0 {
+                ui.small(format!("{} failed", progress.failed));
+            }
+            if ui.button("Stop").clicked() {
+                self.runtime.media.abort_download();
+            }
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(DateRangeSelect::new(
+                &mut self.state.download_start,
+                &mut self.state.download_end,
+                "cache_download_range",
+            ));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.state.download_standard, "Standard quality");
+            ui.checkbox(&mut self.state.download_videos, "Include videos");
+            ui.add(
+                DragValue::new(&mut self.state.download_concurrency)
+                    .range(1..=8)
+                    .prefix("Parallel: "),
+            )
+            .on_hover_text("Requests are still rate limited");
+        });
+
+        let concurrency = self.state.download_concurrency as usize;
+        ui.horizontal(|ui| {
+            if ui
+                .button("Download")
+                .on_hover_text("Downloads all media in the range that is not cached yet")
+                .clicked()
+            {
+                let options = DownloadOptions {
+                    range: ApodDateRange::new(self.state.download_start, self.state.download_end),
+                    variant: if self.state.download_standard {
+                        MediaVariant::Standard
+                    } else {
+                        MediaVariant::Hd
+                    },
+                    images_only: !self.state.download_videos,
+                };
+                self.runtime.media_download_start(options, concurrency);
+            }
+
+            let Some(saved) = self.runtime.media.saved_download() else {
+                return;
+            };
+            let pending = saved.pending.len();
+            let failed = saved.failures.len();
+            let failures = failure_summary(&saved.failures);
+
+            if pending > 0 && ui.button(format!("Resume ({pending} left)")).clicked() {
+                self.runtime.media_download_resume(false, concurrency);
+            }
+            if failed > 0
+                && ui
+                    .button(format!("Retry {failed} failed"))
+                    .on_hover_text(failures)
+                    .clicked()
+            {
+                self.runtime.media_download_resume(true, concurrency);
+            }
+        });
+    }
+
+    fn run(&mut self, operation: CacheOperation) {
+        self.runtime.media_cache_operation(operation);
+    }
+}
+
+impl AppWindow for CacheWindow<'_> {
+    fn id() -> WindowId {
+        WindowId::Cache
+    }
+
+    fn title() -> impl Into<WidgetText> {
+        "Cache"
+    }
+
+    fn is_open(&self) -> bool {
+        self.state.is_open()
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.state.set_open(open);
+    }
+
+    fn render_content(&mut self, ui: &mut Ui) {
+        let is_busy = self.runtime.media.cache_busy();
+        if !self.state.usage_requested {
+            self.state.usage_requested = true;
+            self.run(CacheOperation::Refresh);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!is_busy, Button::new("Refresh")).clicked() {
+                self.run(CacheOperation::Refresh);
+            }
+
+            if let Some(status) = self.runtime.media.cache_status() {
+                ui.spinner();
+                ui.label(status);
+            }
+        });
+
+        ui.separator();
+
+        match self.runtime.media.cache_usage() {
+            Some(usage) => Self::render_usage(ui, usage),
+            None => {
+                ui.small("Calculating usage...");
+            }
+        }
+
+        ui.separator();
+        self.render_eviction(ui, !is_busy);
+
+        ui.separator();
+        ui.strong("Integrity");
+        self.render_verify(ui, !is_busy);
+
+        ui.separator();
+        ui.strong("Download");
+        self.render_download(ui);
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct CacheWindowState {
+    pub is_open: bool,
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u32,
+    #[serde(default = "default_unused_days")]
+    pub unused_days: u32,
+    #[serde(default = "default_range_start")]
+    pub range_start: ApodDate,
+    #[serde(default = "default_range_end")]
+    pub range_end: ApodDate,
+    #[serde(default = "default_range_start")]
+    pub download_start: ApodDate,
+    #[serde(default = "default_download_end")]
+    pub download_end: ApodDate,
+    #[serde(default)]
+    pub download_standard: bool,
+    #[serde(default)]
+    pub download_videos: bool,
+    #[serde(default = "default_download_concurrency")]
+    pub download_concurrency: u32,
+    #[serde(default)]
+    pub verify_delete: bool,
+    #[serde(default)]
+    pub verify_redownload: bool,
+    /// The usage is calculated once when the window is first shown, afterward only on refresh
+    #[serde(skip)]
+    pub usage_requested: bool,
+    /// Clearing the cache asks again after the first click
+    #[serde(skip)]
+    pub confirm_clear: bool,
+}
+
+impl Default for CacheWindowState {
+    fn default() -> Self {
+        Self {
+            is_open: false,
+            max_size_mb: default_max_size_mb(),
+            unused_days: default_unused_days(),
+            range_start: default_range_start(),
+            range_end: default_range_end(),
+            download_start: default_range_start(),
+            download_end: default_download_end(),
+            download_standard: false,
+            download_videos: false,
+            download_concurrency: default_download_concurrency(),
+            verify_delete: false,
+            verify_redownload: false,
+            usage_requested: false,
+            confirm_clear: false,
+        }
+    }
+}
+
+impl ToggleableWindowState for CacheWindowState {
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.is_open = open;
+    }
+
+    fn toggle_label(&self) -> String {
+        egui_phosphor::regular::HARD_DRIVES.to_string()
+    }
+}
+
+fn default_max_size_mb() -> u32 {
+    1024
+}
+
+fn default_unused_days() -> u32 {
+    90
+}
+
+fn default_range_start() -> ApodDate {
+    ApodDate::START
+}
+
+fn default_range_end() -> ApodDate {
+    ApodDate::START
+}
+
+fn default_download_end() -> ApodDate {
+    ApodDate::current(CurrentDateMode::default())
+}
+
+fn default_download_concurrency() -> u32 {
+    2
+}
+
+/// At most a few failures, as hover text
+fn failure_summary(failures: &BTreeMap<(ApodDate, MediaVariant), String>) -> String {
+    let mut lines: Vec<String> = failures
+        .iter()
+        .take(10)
+        .map(|((date, variant), error)| format!("{date} {variant}: {error}"))
+        .collect();
+    if failures.len() > lines.len() {
+        lines.push(format!("and {} more", failures.len() - lines.len()));
+    }
+    lines.join("\n")
+}
+
+fn round_secs(duration: Duration) -> Duration {
+    Duration::from_secs(duration.as_secs())
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
@@ -1,5 +1,6 @@
 use crate::app::actions::AppActions;
 use crate::runtime::apod_data::ApodData;
+use crate::runtime::media_indexer::MediaIndexer;
 use crate::widgets::apod_table::{ApodTable, ApodTableState};
 use crate::windows::{AppWindow, ToggleableWindowState, WindowId};
 use egui::{Ui, Widget, WidgetText};
@@ -8,6 +9,7 @@ pub struct DataWindow<'a> {
     state: &'a mut DataWindowState,
     actions: &'a AppActions,
     apod_data: &'a ApodData,
+    indexer: &'a MediaIndexer,
 }
 
 impl<'a> DataWindow<'a> {
@@ -15,11 +17,13 @@ impl<'a> DataWindow<'a> {
         state: &'a mut DataWindowState,
         actions: &'a AppActions,
         apod_data: &'a ApodData,
+        indexer: &'a MediaIndexer,
     ) -> Self {
         Self {
             state,
             actions,
             apod_data,
+            indexer,
         }
     }
 }
@@ -42,7 +46,13 @@ impl AppWindow for DataWindow<'_> {
     }
 
     fn render_content(&mut self, ui: &mut Ui) {
-        ApodTable::new(&mut self.state.table_state, self.actions, self.apod_data).ui(ui);
+        ApodTable::new(
+            &mut self.state.table_state,
+            self.actions,
+            self.apod_data,
+            self.indexer,
+        )
+        .ui(ui);
     }
 }
 
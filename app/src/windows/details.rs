@@ -1,7 +1,13 @@
+use crate::runtime::media_indexer::DEFAULT_MAX_DISTANCE;
 use crate::runtime::Runtime;
+use crate::widgets::animation_player::AnimationPlayback;
+use crate::windows::cache::format_bytes;
 use crate::windows::{AppWindow, ToggleableWindowState, WindowId};
 use apodex::date::ApodDate;
-use egui::{Ui, WidgetText};
+use apodex::media::index::PerceptualHash;
+use apodex::media::metadata::{ExifData, ImageMetadata};
+use apodex::ApodEntry;
+use egui::{Grid, Hyperlink, Ui, WidgetText};
 
 pub struct DetailsWindow<'a> {
     state: &'a mut DetailsWindowState,
@@ -12,6 +18,87 @@ impl<'a> DetailsWindow<'a> {
     pub fn new(state: &'a mut DetailsWindowState, runtime: &'a mut Runtime) -> Self {
         Self { state, runtime }
     }
+
+    fn render_quality_controls(&mut self, ui: &mut Ui, entry: &ApodEntry) {
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.state.prefer_standard, "Standard quality")
+                .on_hover_text(
+                    "Fetch the smaller standard images, HD versions are loaded on demand",
+                )
+                .changed()
+            {
+                self.runtime
+                    .media
+                    .set_prefer_standard(self.state.prefer_standard);
+            }
+
+            if self.runtime.media.can_load_hd(entry) && ui.button("Load HD").clicked() {
+                self.runtime.media.load_hd(entry);
+            }
+        });
+    }
+
+    /// Properties of the cached image according to the media index
+    fn render_metadata(&mut self, ui: &mut Ui) {
+        let Some(metadata) = self
+            .runtime
+            .indexer
+            .index()
+            .metadata(self.state.current_date)
+        else {
+            return;
+        };
+
+        ui.separator();
+        ui.collapsing("Image details", |ui| {
+            Grid::new("details_metadata_grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    render_image_rows(ui, metadata);
+                    if let Some(exif) = &metadata.exif {
+                        render_exif_rows(ui, exif);
+                    }
+                });
+        });
+    }
+
+    /// Other dates with the same image according to the media index
+    fn render_similar(&mut self, ui: &mut Ui) {
+        let similar = self
+            .runtime
+            .indexer
+            .index()
+            .similar(self.state.current_date, DEFAULT_MAX_DISTANCE);
+        if similar.is_empty() {
+            return;
+        }
+
+        ui.separator();
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Same image also appeared on");
+            for (date, distance) in similar {
+                let title = self
+                    .runtime
+                    .data
+                    .get_entry(date)
+                    .map(|entry| entry.title.clone())
+                    .unwrap_or_default();
+                let hover = if distance == 0 {
+                    title
+                } else {
+                    format!(
+                        "{title}\n{distance} of {} hash bits differ",
+                        PerceptualHash::BITS
+                    )
+                };
+                if ui.link(date.to_string()).on_hover_text(hover).clicked() {
+                    self.state.current_date = date;
+                }
+            }
+        });
+    }
 }
 
 impl AppWindow for DetailsWindow<'_> {
@@ -50,7 +137,11 @@ impl AppWindow for DetailsWindow<'_> {
 
         ui.separator();
 
-        self.runtime.show_image(ui, self.state.current_date);
+        self.render_quality_controls(ui, &entry);
+        self.runtime
+            .show_image(ui, self.state.current_date, &mut self.state.playback);
+        self.render_similar(ui);
+        self.render_metadata(ui);
 
         ui.separator();
 
@@ -60,10 +151,78 @@ impl AppWindow for DetailsWindow<'_> {
     }
 }
 
+fn render_image_rows(ui: &mut Ui, metadata: &ImageMetadata) {
+    let mut row = |label: &str, value: String| {
+        ui.label(label);
+        ui.label(value);
+        ui.end_row();
+    };
+
+    row(
+        "Dimensions",
+        format!(
+            "{} × {} px ({:.1} MP)",
+            metadata.width,
+            metadata.height,
+            metadata.megapixels()
+        ),
+    );
+    row(
+        "File",
+        format!(
+            "{}, {} ({})",
+            metadata.media_type.mime_type(),
+            format_bytes(metadata.file_size),
+            metadata.variant
+        ),
+    );
+    row(
+        "Color",
+        format!("{}, {} bit", metadata.color, metadata.bits_per_channel),
+    );
+    if metadata.is_animated() {
+        row("Frames", metadata.frames.to_string());
+    }
+}
+
+fn render_exif_rows(ui: &mut Ui, exif: &ExifData) {
+    let fields = [
+        ("Camera", exif.camera.clone()),
+        ("Lens", exif.lens.clone()),
+        ("Exposure", exif.exposure_time.clone()),
+        ("Aperture", exif.aperture.clone()),
+        ("ISO", exif.iso.map(|iso| iso.to_string())),
+        ("Focal length", exif.focal_length.clone()),
+        ("Taken", exif.taken.clone()),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            ui.label(label);
+            ui.label(value);
+            ui.end_row();
+        }
+    }
+
+    if let Some(position) = exif.position {
+        let text = format!("{:.5}, {:.5}", position.latitude, position.longitude);
+        let url = format!(
+            "https://www.openstreetmap.org/?mlat={}&mlon={}",
+            position.latitude, position.longitude
+        );
+        ui.label("Location");
+        ui.add(Hyperlink::from_label_and_url(text, url));
+        ui.end_row();
+    }
+}
+
 #[derive(Default, serde::Deserialize, serde::Serialize)]
 pub struct DetailsWindowState {
     pub is_open: bool,
     pub current_date: ApodDate,
+    #[serde(default)]
+    pub prefer_standard: bool,
+    #[serde(skip)]
+    pub playback: AnimationPlayback,
 }
 
 impl ToggleableWindowState for DetailsWindowState {
@@ -0,0 +1,208 @@
+use crate::app::actions::AppActions;
+use crate::runtime::apod_media::fit_to_bounds;
+use crate::runtime::media_indexer::DEFAULT_MAX_DISTANCE;
+use crate::runtime::Runtime;
+use crate::windows::{AppWindow, ToggleableWindowState, WindowId};
+use apodex::date::ApodDate;
+use apodex::media::index::PerceptualHash;
+use egui::{Button, CursorIcon, DragValue, Frame, Label, ScrollArea, Sense, Ui, Vec2, WidgetText};
+
+const THUMBNAIL_SIZE: f32 = 96.0;
+/// Thumbnail, date and title of a card
+const GROUP_HEIGHT: f32 = THUMBNAIL_SIZE + 56.0;
+
+pub struct DuplicatesWindow<'a> {
+    state: &'a mut DuplicatesWindowState,
+    actions: &'a AppActions,
+    runtime: &'a mut Runtime,
+}
+
+impl<'a> DuplicatesWindow<'a> {
+    pub fn new(
+        state: &'a mut DuplicatesWindowState,
+        actions: &'a AppActions,
+        runtime: &'a mut Runtime,
+    ) -> Self {
+        Self {
+            state,
+            actions,
+            runtime,
+        }
+    }
+
+    fn render_controls(&mut self, ui: &mut Ui) {
+        let is_busy = self.runtime.indexer.is_busy();
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!is_busy, Button::new("Update index"))
+                .on_hover_text("Indexes cached images that are not indexed yet")
+                .clicked()
+            {
+                self.runtime.media_index_update();
+            }
+
+            if let Some(status) = self.runtime.indexer.status() {
+                ui.spinner();
+                ui.label(status);
+            } else {
+                ui.label(format!(
+                    "{} images indexed",
+                    self.runtime.indexer.index().len()
+                ));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Max difference");
+            ui.add(
+                DragValue::new(&mut self.state.max_distance)
+                    .range(0..=PerceptualHash::BITS / 4)
+                    .suffix(" bits"),
+            )
+            .on_hover_text("Higher finds edited and cropped versions, but also unrelated images");
+        });
+    }
+
+    /// Grouping compares every pair of images, so it's only redone when something changed
+    fn update_groups(&mut self) {
+        let key = (self.runtime.indexer.generation(), self.state.max_distance);
+        if self.state.groups_key != Some(key) {
+            self.state.groups = self
+                .runtime
+                .indexer
+                .index()
+                .duplicate_groups(self.state.max_distance);
+            self.state.groups_key = Some(key);
+        }
+    }
+
+    fn render_card(&mut self, ui: &mut Ui, date: ApodDate) {
+        let title = self
+            .runtime
+            .data
+            .get_entry(date)
+            .map(|entry| entry.title.clone())
+            .unwrap_or_default();
+
+        let response = Frame::group(ui.style())
+            .show(ui, |ui| {
+                ui.set_width(THUMBNAIL_SIZE);
+                ui.vertical_centered(|ui| {
+                    let thumbnail_size = Vec2::splat(THUMBNAIL_SIZE);
+                    let pixels = THUMBNAIL_SIZE * ui.ctx().pixels_per_point();
+                    match self.runtime.media.get_thumbnail(date, pixels) {
+                        Some((id, aspect)) => {
+                            let size = fit_to_bounds(thumbnail_size, aspect);
+                            ui.add_sized(thumbnail_size, egui::Image::new((id, size)));
+                        }
+                        None => {
+                            ui.add_sized(thumbnail_size, egui::Spinner::new());
+                        }
+                    }
+                    ui.small(date.to_string());
+                    ui.add(Label::new(&title).truncate());
+                });
+            })
+            .response
+            .interact(Sense::click())
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .on_hover_text(&title);
+
+        if response.clicked() {
+            self.actions.details_select_date(date);
+            self.actions.open_and_focus_window(WindowId::Details);
+        }
+    }
+}
+
+impl AppWindow for DuplicatesWindow<'_> {
+    fn id() -> WindowId {
+        WindowId::Duplicates
+    }
+
+    fn title() -> impl Into<WidgetText> {
+        "Duplicates"
+    }
+
+    fn is_open(&self) -> bool {
+        self.state.is_open()
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.state.set_open(open);
+    }
+
+    fn render_content(&mut self, ui: &mut Ui) {
+        self.render_controls(ui);
+        ui.separator();
+
+        self.update_groups();
+        let groups = std::mem::take(&mut self.state.groups);
+        if groups.is_empty() {
+            ui.small("No images found more than once, cached images have to be indexed first.");
+        } else {
+            ui.label(format!(
+                "{} images shown on {} dates",
+                groups.len(),
+                groups.iter().map(Vec::len).sum::<usize>()
+            ));
+
+            // Only visible groups are rendered, so only their thumbnails are requested
+            ScrollArea::vertical().show_rows(ui, GROUP_HEIGHT, groups.len(), |ui, rows| {
+                for group in &groups[rows] {
+                    ScrollArea::horizontal()
+                        .id_salt(("duplicate_group", group[0]))
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                for date in group {
+                                    self.render_card(ui, *date);
+                                }
+                            });
+                        });
+                }
+            });
+        }
+        self.state.groups = groups;
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct DuplicatesWindowState {
+    pub is_open: bool,
+    #[serde(default = "default_max_distance")]
+    pub max_distance: u32,
+    #[serde(skip)]
+    groups: Vec<Vec<ApodDate>>,
+    /// Index generation and distance the groups were computed for
+    #[serde(skip)]
+    groups_key: Option<(u64, u32)>,
+}
+
+impl Default for DuplicatesWindowState {
+    fn default() -> Self {
+        Self {
+            is_open: false,
+            max_distance: default_max_distance(),
+            groups: Vec::new(),
+            groups_key: None,
+        }
+    }
+}
+
+impl ToggleableWindowState for DuplicatesWindowState {
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.is_open = open;
+    }
+
+    fn toggle_label(&self) -> String {
+        egui_phosphor::regular::COPY.to_string()
+    }
+}
+
+fn default_max_distance() -> u32 {
+    DEFAULT_MAX_DISTANCE
+}
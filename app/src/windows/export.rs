@@ -1,7 +1,15 @@
 use crate::runtime::file_picker::PickTarget;
 use crate::runtime::Runtime;
+use crate::widgets::date_range_select::DateRangeSelect;
+use crate::widgets::enum_select::EnumSelect;
 use crate::windows::{AppWindow, ToggleableWindowState};
-use egui::{Button, Ui, WidgetText};
+use apodex::date::range::ApodDateRange;
+use apodex::date::ApodDate;
+use apodex::exporting::epub::EpubOptions;
+use apodex::exporting::{ExportColumn, ExportFormat, ExportOptions};
+use egui::{Button, Ui, Widget, WidgetText};
+use std::fmt::{Display, Formatter};
+use strum_macros::EnumIter;
 
 pub struct ExportWindow<'a> {
     state: &'a mut ExportWindowState,
@@ -12,26 +20,8 @@ impl<'a> ExportWindow<'a> {
     pub fn new(state: &'a mut ExportWindowState, runtime: &'a mut Runtime) -> Self {
         Self { state, runtime }
     }
-}
-
-impl AppWindow for ExportWindow<'_> {
-    fn id() -> crate::windows::WindowId {
-        crate::windows::WindowId::Export
-    }
-
-    fn title() -> impl Into<WidgetText> {
-        "Export"
-    }
-
-    fn is_open(&self) -> bool {
-        self.state.is_open()
-    }
-
-    fn set_open(&mut self, open: bool) {
-        self.state.set_open(open);
-    }
 
-    fn render_content(&mut self, ui: &mut Ui) {
+    fn render_html_export(&mut self, ui: &mut Ui) {
         let data_available = self.runtime.data.latest_html_date().is_some();
         let is_loading = self.runtime.data.save_busy();
 
@@ -49,6 +39,32 @@ impl AppWindow for ExportWindow<'_> {
                     .file_picker
                     .open_save(PickTarget::SaveHtmlArchive, file_name);
             }
+
+            let button_response = ui.add_enabled(
+                !is_loading && data_available,
+                Button::new("Export sharded HTML archive"),
+            );
+            if button_response.clicked() {
+                self.runtime
+                    .file_picker
+                    .open_directory(PickTarget::SaveShardedHtmlArchive);
+            }
+
+            let button_response = ui
+                .add_enabled(
+                    !is_loading && data_available,
+                    Button::new("Export update patch"),
+                )
+                .on_hover_text("Exports all entries added or changed since the included archive");
+            if button_response.clicked()
+                && let Some(latest_date) = self.runtime.data.latest_entry_date()
+            {
+                let date = latest_date.format("%Y-%m-%d").to_string();
+                let file_name = format!("apodex-html-patch-{}.bin", date);
+                self.runtime
+                    .file_picker
+                    .open_save(PickTarget::SaveHtmlPatch, file_name);
+            }
         });
 
         if is_loading {
@@ -61,14 +77,258 @@ impl AppWindow for ExportWindow<'_> {
         }
     }
 
+    fn render_entry_export(&mut self, ui: &mut Ui) {
+        let data_available = self.runtime.data.latest_entry_date().is_some();
+        let is_exporting = self.runtime.data.export_busy();
+
+        EnumSelect::new(&mut self.state.entry_format, "entry_export_format")
+            .label("Format")
+            .ui(ui);
+
+        let uses_columns = self.state.entry_format != EntryExportFormat::Sqlite;
+        if uses_columns {
+            ui.horizontal_wrapped(|ui| {
+                for column in ExportColumn::ALL {
+                    let mut selected = self.state.entry_columns.contains(&column);
+                    if ui.checkbox(&mut selected, column.to_string()).changed() {
+                        self.state.set_column(column, selected);
+                    }
+                }
+            });
+        }
+
+        let has_columns = !uses_columns || !self.state.entry_columns.is_empty();
+        let button_response = ui.add_enabled(
+            !is_exporting && data_available && has_columns,
+            Button::new("Export entries"),
+        );
+        if button_response.clicked()
+            && let Some(latest_date) = self.runtime.data.latest_entry_date()
+        {
+            let date = latest_date.format("%Y-%m-%d").to_string();
+            let extension = self.state.entry_format.extension();
+            let file_name = format!("apodex-entries-{}.{}", date, extension);
+            self.runtime
+                .file_picker
+                .open_save(PickTarget::ExportEntries, file_name);
+        }
+
+        if is_exporting {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                if let Some(status) = self.runtime.data.export_status() {
+                    ui.label(status);
+                }
+            });
+        }
+    }
+
+    fn render_epub_export(&mut self, ui: &mut Ui) {
+        let data_available = self.runtime.data.latest_entry_date().is_some();
+        let is_exporting = self.runtime.data.export_busy();
+
+        ui.horizontal(|ui| {
+            ui.label("E-book from");
+            DateRangeSelect::new(
+                &mut self.state.epub_start,
+                &mut self.state.epub_end,
+                "epub_range",
+            )
+            .ui(ui);
+        });
+
+        let button_response = ui
+            .add_enabled(!is_exporting && data_available, Button::new("Export EPUB"))
+            .on_hover_text("Includes the images that are already in the media cache");
+        if button_response.clicked() {
+            let file_name = format!(
+                "apod-{}-{}.epub",
+                self.state.epub_start, self.state.epub_end
+            );
+            self.runtime
+                .file_picker
+                .open_save(PickTarget::ExportEpub, file_name);
+        }
+    }
+
+    fn render_vault_export(&mut self, ui: &mut Ui) {
+        let data_available = self.runtime.data.latest_entry_date().is_some();
+        let is_exporting = self.runtime.data.export_busy();
+
+        let button_response = ui
+            .add_enabled(
+                !is_exporting && data_available,
+                Button::new("Export Markdown vault"),
+            )
+            .on_hover_text("One note per entry with links between them and cached images");
+        if button_response.clicked() {
+            self.runtime
+                .file_picker
+                .open_directory(PickTarget::ExportMarkdownVault);
+        }
+    }
+}
+
+impl AppWindow for ExportWindow<'_> {
+    fn id() -> crate::windows::WindowId {
+        crate::windows::WindowId::Export
+    }
+
+    fn title() -> impl Into<WidgetText> {
+        "Export"
+    }
+
+    fn is_open(&self) -> bool {
+        self.state.is_open()
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.state.set_open(open);
+    }
+
+    fn render_content(&mut self, ui: &mut Ui) {
+        self.render_html_export(ui);
+        ui.separator();
+        self.render_entry_export(ui);
+        ui.separator();
+        self.render_epub_export(ui);
+        ui.separator();
+        self.render_vault_export(ui);
+    }
+
     fn resizable(&self) -> bool {
         false
     }
 }
 
-#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, EnumIter, serde::Serialize, serde::Deserialize,
+)]
+pub enum EntryExportFormat {
+    #[default]
+    Json,
+    Ndjson,
+    Csv,
+    Sqlite,
+}
+
+impl EntryExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            EntryExportFormat::Json => ExportFormat::Json.extension(),
+            EntryExportFormat::Ndjson => ExportFormat::Ndjson.extension(),
+            EntryExportFormat::Csv => ExportFormat::Csv.extension(),
+            EntryExportFormat::Sqlite => "sqlite",
+        }
+    }
+}
+
+impl Display for EntryExportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryExportFormat::Json => write!(f, "JSON"),
+            EntryExportFormat::Ndjson => write!(f, "NDJSON"),
+            EntryExportFormat::Csv => write!(f, "CSV"),
+            EntryExportFormat::Sqlite => write!(f, "SQLite"),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
 pub struct ExportWindowState {
     pub is_open: bool,
+    #[serde(default)]
+    pub entry_format: EntryExportFormat,
+    #[serde(default = "default_entry_columns")]
+    pub entry_columns: Vec<ExportColumn>,
+    #[serde(default = "default_epub_start")]
+    pub epub_start: ApodDate,
+    #[serde(default = "default_epub_end")]
+    pub epub_end: ApodDate,
+}
+
+/// How the entries are written to the picked file
+pub enum EntryExport {
+    Rows(ExportOptions),
+    /// Always contains everything and ignores the columns
+    Sqlite,
+}
+
+impl ExportWindowState {
+    pub fn entry_export(&self) -> EntryExport {
+        let format = match self.entry_format {
+            EntryExportFormat::Json => ExportFormat::Json,
+            EntryExportFormat::Ndjson => ExportFormat::Ndjson,
+            EntryExportFormat::Csv => ExportFormat::Csv,
+            EntryExportFormat::Sqlite => return EntryExport::Sqlite,
+        };
+
+        EntryExport::Rows(ExportOptions {
+            format,
+            columns: self.entry_columns.clone(),
+        })
+    }
+
+    /// Titled after the year if the range covers exactly one
+    pub fn epub_options(&self) -> EpubOptions {
+        let range = ApodDateRange::new(self.epub_start, self.epub_end);
+        if let Some(options) = EpubOptions::year(self.epub_start.year())
+            && options.range == range
+        {
+            return options;
+        }
+
+        EpubOptions {
+            title: format!("APOD {} to {}", range.start(), range.end()),
+            range,
+            language: "en".to_string(),
+        }
+    }
+
+    /// Keeps the selected columns in their canonical order
+    fn set_column(&mut self, column: ExportColumn, selected: bool) {
+        let is_selected = |c: &ExportColumn| {
+            if *c == column {
+                selected
+            } else {
+                self.entry_columns.contains(c)
+            }
+        };
+        self.entry_columns = ExportColumn::ALL.into_iter().filter(is_selected).collect();
+    }
+}
+
+impl Default for ExportWindowState {
+    fn default() -> Self {
+        Self {
+            is_open: false,
+            entry_format: EntryExportFormat::default(),
+            entry_columns: default_entry_columns(),
+            epub_start: default_epub_start(),
+            epub_end: default_epub_end(),
+        }
+    }
+}
+
+fn default_entry_columns() -> Vec<ExportColumn> {
+    ExportColumn::ALL.to_vec()
+}
+
+/// Defaults to the last complete year
+fn default_epub_range() -> Option<ApodDateRange> {
+    ApodDateRange::year(ApodDate::today().year() - 1)
+}
+
+fn default_epub_start() -> ApodDate {
+    default_epub_range()
+        .map(|range| range.start())
+        .unwrap_or_default()
+}
+
+fn default_epub_end() -> ApodDate {
+    default_epub_range()
+        .map(|range| range.end())
+        .unwrap_or_default()
 }
 
 impl ToggleableWindowState for ExportWindowState {
@@ -1,7 +1,11 @@
 use crate::runtime::file_picker::PickTarget;
 use crate::runtime::Runtime;
+use crate::widgets::enum_select::EnumSelect;
 use crate::windows::{AppWindow, ToggleableWindowState, WindowId};
-use egui::{Button, Ui, WidgetText};
+use apodex::importing::ImportFormat;
+use egui::{Button, Ui, Widget, WidgetText};
+use std::fmt::{Display, Formatter};
+use strum_macros::EnumIter;
 
 pub struct ImportWindow<'a> {
     state: &'a mut ImportWindowState,
@@ -12,26 +16,8 @@ impl<'a> ImportWindow<'a> {
     pub fn new(state: &'a mut ImportWindowState, runtime: &'a mut Runtime) -> Self {
         Self { state, runtime }
     }
-}
-
-impl AppWindow for ImportWindow<'_> {
-    fn id() -> WindowId {
-        WindowId::Import
-    }
-
-    fn title() -> impl Into<WidgetText> {
-        "Import"
-    }
-
-    fn is_open(&self) -> bool {
-        self.state.is_open()
-    }
-
-    fn set_open(&mut self, open: bool) {
-        self.state.set_open(open);
-    }
 
-    fn render_content(&mut self, ui: &mut Ui) {
+    fn render_html_import(&mut self, ui: &mut Ui) {
         let is_loading = self.runtime.data.load_busy();
 
         ui.horizontal(|ui| {
@@ -41,6 +27,23 @@ impl AppWindow for ImportWindow<'_> {
                     .file_picker
                     .open_single(PickTarget::LoadHtmlArchive);
             }
+
+            let button_response =
+                ui.add_enabled(!is_loading, Button::new("Import sharded HTML archive"));
+            if button_response.clicked() {
+                self.runtime
+                    .file_picker
+                    .open_directory(PickTarget::LoadShardedHtmlArchive);
+            }
+
+            let button_response = ui
+                .add_enabled(!is_loading, Button::new("Import update patch"))
+                .on_hover_text("Applies an update patch on top of the included archive");
+            if button_response.clicked() {
+                self.runtime
+                    .file_picker
+                    .open_single(PickTarget::LoadHtmlPatch);
+            }
         });
 
         if is_loading {
@@ -53,14 +56,174 @@ impl AppWindow for ImportWindow<'_> {
         }
     }
 
+    fn render_entry_import(&mut self, ui: &mut Ui) {
+        let is_importing = self.runtime.data.import_busy();
+
+        ui.horizontal(|ui| {
+            EnumSelect::new(&mut self.state.entry_format, "entry_import_format")
+                .label("Format")
+                .ui(ui);
+
+            let button_response = ui
+                .add_enabled(!is_importing, Button::new("Import entries"))
+                .on_hover_text("Imported entries replace existing entries of the same date");
+            if button_response.clicked() {
+                self.runtime
+                    .file_picker
+                    .open_single(PickTarget::ImportEntries);
+            }
+        });
+
+        if is_importing {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                if let Some(status) = self.runtime.data.import_status() {
+                    ui.label(status);
+                }
+            });
+        }
+
+        self.render_imported_entries(ui, is_importing);
+
+        let Some(summary) = self.runtime.data.last_import() else {
+            return;
+        };
+
+        ui.label(format!(
+            "Last import: {}/{} entries",
+            summary.imported, summary.rows
+        ));
+        ui.small(format!(
+            "Saved to {}, loaded again on startup",
+            summary.saved_to.display()
+        ));
+
+        if !summary.issues.is_empty() {
+            ui.collapsing(format!("Issues ({})", summary.issues.len()), |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for issue in summary.issues.iter() {
+                            ui.label(format!("Row {}: {}", issue.row, issue.error));
+                        }
+                    });
+            });
+        }
+    }
+
+    fn render_imported_entries(&mut self, ui: &mut Ui, is_importing: bool) {
+        let imported_entries = self.runtime.data.imported_entries();
+        let error = self.runtime.data.imported_entries_error();
+        if imported_entries == 0 && error.is_none() {
+            return;
+        }
+
+        if let Some(error) = error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        } else {
+            ui.label(format!(
+                "{imported_entries} imported entries are loaded on startup"
+            ));
+        }
+
+        let enabled = !is_importing && !self.runtime.data.load_busy();
+        if self.state.confirm_forget {
+            ui.horizontal(|ui| {
+                ui.label("Delete the imported entries and reload the HTML archive?");
+                if ui.add_enabled(enabled, Button::new("Forget")).clicked() {
+                    self.state.confirm_forget = false;
+                    self.runtime.data_forget_imported_entries();
+                }
+                if ui.button("Cancel").clicked() {
+                    self.state.confirm_forget = false;
+                }
+            });
+        } else if ui
+            .add_enabled(enabled, Button::new("Forget imported entries"))
+            .on_hover_text("Parsed entries are used again for the dates that were imported")
+            .clicked()
+        {
+            self.state.confirm_forget = true;
+        }
+    }
+}
+
+impl AppWindow for ImportWindow<'_> {
+    fn id() -> WindowId {
+        WindowId::Import
+    }
+
+    fn title() -> impl Into<WidgetText> {
+        "Import"
+    }
+
+    fn is_open(&self) -> bool {
+        self.state.is_open()
+    }
+
+    fn set_open(&mut self, open: bool) {
+        self.state.set_open(open);
+    }
+
+    fn render_content(&mut self, ui: &mut Ui) {
+        self.render_html_import(ui);
+        ui.separator();
+        self.render_entry_import(ui);
+    }
+
     fn resizable(&self) -> bool {
         false
     }
 }
 
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, EnumIter, serde::Serialize, serde::Deserialize,
+)]
+pub enum EntryImportFormat {
+    #[default]
+    Json,
+    Ndjson,
+    Csv,
+    Sqlite,
+}
+
+impl Display for EntryImportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryImportFormat::Json => write!(f, "JSON"),
+            EntryImportFormat::Ndjson => write!(f, "NDJSON"),
+            EntryImportFormat::Csv => write!(f, "CSV"),
+            EntryImportFormat::Sqlite => write!(f, "SQLite"),
+        }
+    }
+}
+
 #[derive(Default, serde::Deserialize, serde::Serialize)]
 pub struct ImportWindowState {
     pub is_open: bool,
+    #[serde(default)]
+    pub entry_format: EntryImportFormat,
+    /// Forgetting imported entries asks again after the first click
+    #[serde(skip)]
+    pub confirm_forget: bool,
+}
+
+/// How the picked file is read
+pub enum EntryImport {
+    Rows(ImportFormat),
+    /// Not row based, the database is only read
+    Sqlite,
+}
+
+impl ImportWindowState {
+    pub fn entry_import(&self) -> EntryImport {
+        match self.entry_format {
+            EntryImportFormat::Json => EntryImport::Rows(ImportFormat::Json),
+            EntryImportFormat::Ndjson => EntryImport::Rows(ImportFormat::Ndjson),
+            EntryImportFormat::Csv => EntryImport::Rows(ImportFormat::Csv),
+            EntryImportFormat::Sqlite => EntryImport::Sqlite,
+        }
+    }
 }
 
 impl ToggleableWindowState for ImportWindowState {
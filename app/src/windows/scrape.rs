@@ -1,16 +1,27 @@
+use crate::app::actions::AppActions;
 use crate::runtime::Runtime;
 use crate::windows::{AppWindow, ToggleableWindowState, WindowId};
-use egui::{Button, Grid, Ui, WidgetText};
+use apodex::date::CurrentDateMode;
+use egui::{Button, DragValue, Grid, Ui, WidgetText};
 use std::time::Duration;
 
 pub struct ScrapeWindow<'a> {
     state: &'a mut ScrapeWindowState,
+    actions: &'a AppActions,
     runtime: &'a mut Runtime,
 }
 
 impl<'a> ScrapeWindow<'a> {
-    pub fn new(state: &'a mut ScrapeWindowState, runtime: &'a mut Runtime) -> Self {
-        Self { state, runtime }
+    pub fn new(
+        state: &'a mut ScrapeWindowState,
+        actions: &'a AppActions,
+        runtime: &'a mut Runtime,
+    ) -> Self {
+        Self {
+            state,
+            actions,
+            runtime,
+        }
     }
 }
 
@@ -47,6 +58,18 @@ impl AppWindow for ScrapeWindow<'_> {
                 };
                 ui.end_row();
 
+                let registry = self.runtime.data.missing_dates();
+                ui.label("Confirmed missing");
+                ui.label(registry.confirmed().len().to_string());
+                ui.end_row();
+
+                let due = registry.due_for_verification().len();
+                if due > 0 {
+                    ui.label("Due for verification");
+                    ui.label(due.to_string());
+                    ui.end_row();
+                }
+
                 if self.runtime.scraper.is_busy() {
                     let eta = Duration::from_secs(self.runtime.scraper.queue_len() as u64 * 2);
                     ui.label("ETA");
@@ -57,6 +80,19 @@ impl AppWindow for ScrapeWindow<'_> {
 
         ui.separator();
 
+        let checkbox_response = ui
+            .checkbox(&mut self.state.use_local_date, "Use local date")
+            .on_hover_text(
+                "Count today's entry as missing from local midnight on, instead of when it is published in US Eastern time",
+            );
+        if checkbox_response.changed() {
+            self.runtime
+                .data
+                .set_current_date_mode(self.state.current_date_mode());
+        }
+
+        self.render_reverify_setting(ui);
+
         ui.horizontal(|ui| {
             let can_scrape = self.runtime.data.has_missing()
                 && !self.runtime.scraper.is_busy()
@@ -86,9 +122,51 @@ impl AppWindow for ScrapeWindow<'_> {
     }
 }
 
+impl ScrapeWindow<'_> {
+    fn render_reverify_setting(&mut self, ui: &mut Ui) {
+        let reverify_after_days = self.runtime.data.missing_dates().reverify_after_days();
+        let mut reverify = reverify_after_days.is_some();
+        let mut days = reverify_after_days.unwrap_or(30);
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut reverify, "Re-verify missing pages after")
+                .on_hover_text(
+                    "Pages that were not found are downloaded again once this period has passed",
+                );
+            ui.add_enabled(
+                reverify,
+                DragValue::new(&mut days).range(1..=3650).suffix(" days"),
+            );
+        });
+
+        let new_reverify_after_days = reverify.then_some(days);
+        if new_reverify_after_days != reverify_after_days
+            && let Err(err) = self
+                .runtime
+                .data
+                .set_reverify_after_days(new_reverify_after_days)
+        {
+            self.actions
+                .toast_error(format!("Failed to save missing dates: {err}"));
+        }
+    }
+}
+
 #[derive(Default, serde::Deserialize, serde::Serialize)]
 pub struct ScrapeWindowState {
     pub is_open: bool,
+    #[serde(default)]
+    pub use_local_date: bool,
+}
+
+impl ScrapeWindowState {
+    pub fn current_date_mode(&self) -> CurrentDateMode {
+        if self.use_local_date {
+            CurrentDateMode::Local
+        } else {
+            CurrentDateMode::Publication
+        }
+    }
 }
 
 impl ToggleableWindowState for ScrapeWindowState {
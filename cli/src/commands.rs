@@ -0,0 +1,4 @@
+pub mod download;
+pub mod feed;
+pub mod site;
+pub mod verify;
@@ -0,0 +1,151 @@
+use crate::input::ArchiveArgs;
+use apodex::client::reqwest::ReqwestClient;
+use apodex::date::range::ApodDateRange;
+use apodex::date::CurrentDateMode;
+use apodex::media::download::{BulkDownload, DownloadOptions, DownloadProgress};
+use apodex::media::fs::FsMediaCache;
+use apodex::media::MediaVariant;
+use clap::Args;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const STATE_FILE: &str = "download-queue.bin";
+
+#[derive(Args)]
+pub struct DownloadArgs {
+    #[command(flatten)]
+    input: ArchiveArgs,
+    /// Media directory, files are named like 2005/03/ap050304-hd.jpg
+    #[arg(long)]
+    media: PathBuf,
+    /// Date range like START..END, YYYY or YYYY-MM, defaults to everything
+    #[arg(long)]
+    range: Option<ApodDateRange>,
+    /// Download the standard versions instead of the HD ones
+    #[arg(long)]
+    standard: bool,
+    /// Also download videos and other media that is not an image
+    #[arg(long)]
+    all_media: bool,
+    /// Downloads in flight at once, requests are still rate limited
+    #[arg(long, default_value_t = 2)]
+    concurrency: usize,
+    /// Queue the failed files of the saved job again
+    #[arg(long)]
+    retry_failed: bool,
+    /// Plan a new job even if an unfinished one is saved
+    #[arg(long)]
+    restart: bool,
+}
+
+pub fn run(args: DownloadArgs) -> anyhow::Result<()> {
+    let archive = args.input.load_entries()?;
+    let mut cache = FsMediaCache::new(&args.media).map_err(|err| anyhow::anyhow!(err))?;
+    let state_file = args.media.join(STATE_FILE);
+
+    let saved = if state_file.exists() && !args.restart {
+        Some(BulkDownload::load(&state_file)?)
+    } else {
+        None
+    };
+
+    let mut job = match saved {
+        Some(mut job) if !job.is_finished() || args.retry_failed => {
+            // The saved job keeps its own range and options, these would be ignored silently
+            if args.range.is_some() || args.standard || args.all_media {
+                anyhow::bail!(
+                    "A saved job in {} is not finished yet, pass --restart to plan a new one with --range, --standard or --all-media",
+                    state_file.display()
+                );
+            }
+            let retried = if args.retry_failed {
+                job.retry_failed()
+            } else {
+                0
+            };
+            println!(
+                "Resuming saved job with {} pending files ({} retried)",
+                job.queue().pending.len(),
+                retried
+            );
+            job
+        }
+        _ => {
+            let options = DownloadOptions {
+                range: args
+                    .range
+                    .unwrap_or_else(|| ApodDateRange::till_current(CurrentDateMode::default())),
+                variant: if args.standard {
+                    MediaVariant::Standard
+                } else {
+                    MediaVariant::Hd
+                },
+                images_only: !args.all_media,
+            };
+            let job = BulkDownload::plan(&archive, &cache, &options)?.with_state_file(&state_file);
+            println!(
+                "Planned {} files in {}",
+                job.queue().pending.len(),
+                options.range
+            );
+            job
+        }
+    };
+    job = job.with_concurrency(args.concurrency);
+    job.save()?;
+
+    let client = ReqwestClient::default();
+    let progress = tokio::runtime::Runtime::new()?.block_on(job.run(
+        &client,
+        &mut cache,
+        &archive,
+        print_progress,
+    ))?;
+    eprintln!();
+
+    println!(
+        "Downloaded {} files ({}) into {}",
+        progress.completed,
+        format_megabytes(progress.bytes),
+        args.media.display()
+    );
+    if progress.failed > 0 {
+        println!(
+            "{} files failed, run again with --retry-failed to retry them",
+            progress.failed
+        );
+        for ((date, variant), error) in &job.queue().failures {
+            eprintln!("{} {}: {}", date, variant, error);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_progress(progress: &DownloadProgress) {
+    let eta = progress.eta.map(format_duration).unwrap_or_default();
+    eprint!(
+        "\r{}/{} done, {} failed, {} ETA {}   ",
+        progress.completed + progress.failed,
+        progress.total(),
+        progress.failed,
+        format_megabytes(progress.bytes),
+        eta
+    );
+    let _ = std::io::stderr().flush();
+}
+
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,82 @@
+use crate::input::ArchiveArgs;
+use apodex::date::range::ApodDateRange;
+use apodex::date::ApodDate;
+use apodex::exporting::feed::{
+    write_feed, write_feed_to_file, FeedFormat, FeedOptions, FeedSelection,
+};
+use apodex::media::fs::FsMediaCache;
+use apodex::media::MediaCache;
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Format {
+    Rss,
+    Atom,
+}
+
+#[derive(Args)]
+pub struct FeedArgs {
+    #[command(flatten)]
+    input: ArchiveArgs,
+    #[arg(long, value_enum, default_value = "rss")]
+    format: Format,
+    /// Number of latest entries, ignored if a date range is given
+    #[arg(long, default_value_t = 30)]
+    latest: usize,
+    /// First date of the range, e.g. 2005-03-04, 050304 or -7d
+    #[arg(long, requires = "to", conflicts_with = "range")]
+    from: Option<ApodDate>,
+    /// Last date of the range, e.g. 2005-03-04, 050304 or today
+    #[arg(long, requires = "from", conflicts_with = "range")]
+    to: Option<ApodDate>,
+    /// Date range like START..END, YYYY or YYYY-MM
+    #[arg(long)]
+    range: Option<ApodDateRange>,
+    #[arg(long, default_value_t = FeedOptions::default().title)]
+    title: String,
+    /// Public URL of the feed, used as its id
+    #[arg(long)]
+    feed_url: Option<String>,
+    /// Media directory, as used by the download command. RSS feeds only enclose cached media,
+    /// since the length of the files is required.
+    #[arg(long)]
+    media: Option<PathBuf>,
+    /// Output file, prints to stdout if omitted
+    #[arg(long, short)]
+    out: Option<PathBuf>,
+}
+
+pub fn run(args: FeedArgs) -> anyhow::Result<()> {
+    let archive = args.input.load_entries()?;
+
+    let selection = match (args.range, args.from, args.to) {
+        (Some(range), _, _) => FeedSelection::Range(range),
+        (None, Some(start), Some(end)) => FeedSelection::Range(ApodDateRange::new(start, end)),
+        _ => FeedSelection::Latest(args.latest),
+    };
+    let options = FeedOptions {
+        format: match args.format {
+            Format::Rss => FeedFormat::Rss,
+            Format::Atom => FeedFormat::Atom,
+        },
+        selection,
+        title: args.title,
+        feed_url: args.feed_url,
+        ..Default::default()
+    };
+
+    let cache = args
+        .media
+        .map(FsMediaCache::new)
+        .transpose()
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let media = cache.as_ref().map(|cache| cache as &dyn MediaCache);
+
+    match args.out {
+        Some(path) => write_feed_to_file(&archive, media, &options, &path)?,
+        None => write_feed(&archive, media, &options, std::io::stdout().lock())?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use crate::input::ArchiveArgs;
+use apodex::exporting::site::{export_site, SiteOptions};
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct SiteArgs {
+    #[command(flatten)]
+    input: ArchiveArgs,
+    /// Directory with media files named apYYMMDD.* or YYYY-MM-DD.*, e.g. a file media cache
+    #[arg(long)]
+    media: Option<PathBuf>,
+    /// Output directory of the site
+    #[arg(long, short)]
+    out: PathBuf,
+    #[arg(long, default_value_t = SiteOptions::default().title)]
+    title: String,
+    /// Maximum thumbnail width and height in pixels
+    #[arg(long, default_value_t = SiteOptions::default().thumbnail_size)]
+    thumbnail_size: u32,
+}
+
+pub fn run(args: SiteArgs) -> anyhow::Result<()> {
+    let archive = args.input.load_entries()?;
+    let options = SiteOptions {
+        title: args.title,
+        thumbnail_size: args.thumbnail_size,
+    };
+
+    println!("Rendering {} entries...", archive.len());
+    let report = export_site(&archive, args.media.as_deref(), &args.out, &options)?;
+
+    println!(
+        "Wrote {} pages, {} media files and {} thumbnails to {}",
+        report.pages,
+        report.media_files,
+        report.thumbnails,
+        args.out.display()
+    );
+    if !report.missing_media.is_empty() {
+        println!(
+            "{} image entries have no local media and link the original",
+            report.missing_media.len()
+        );
+    }
+    for date in report.failed_thumbnails {
+        eprintln!("Could not create a thumbnail for {}", date);
+    }
+
+    Ok(())
+}
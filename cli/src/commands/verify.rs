@@ -0,0 +1,60 @@
+use crate::commands::download::STATE_FILE;
+use apodex::media::download::{BulkDownload, DownloadQueue};
+use apodex::media::fs::FsMediaCache;
+use apodex::media::verify::verify_cache;
+use clap::Args;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Media directory, as used by the download command
+    #[arg(long)]
+    media: PathBuf,
+    /// Delete corrupt files
+    #[arg(long)]
+    delete: bool,
+    /// Queue corrupt files to be downloaded again by the next download run
+    #[arg(long)]
+    requeue: bool,
+}
+
+pub fn run(args: VerifyArgs) -> anyhow::Result<()> {
+    let mut cache = FsMediaCache::new(&args.media).map_err(|err| anyhow::anyhow!(err))?;
+
+    let report = verify_cache(&cache, |checked, total| {
+        eprint!("\r{checked}/{total} checked   ");
+        let _ = std::io::stderr().flush();
+    })?;
+    eprintln!();
+
+    for corrupt in &report.corrupt {
+        println!("{} {}: {}", corrupt.date, corrupt.variant, corrupt.problem);
+    }
+    println!(
+        "Checked {} files, {} corrupt",
+        report.checked,
+        report.corrupt.len()
+    );
+
+    if args.delete {
+        let deleted = report.delete_corrupt(&mut cache)?;
+        println!("Deleted {deleted} files");
+    }
+
+    if args.requeue {
+        let state_file = args.media.join(STATE_FILE);
+        let mut job = if state_file.exists() {
+            BulkDownload::load(&state_file)?
+        } else {
+            BulkDownload::from_queue(DownloadQueue::default()).with_state_file(&state_file)
+        };
+        let downloadable = report.downloadable();
+        let queued = downloadable.len();
+        job.enqueue(downloadable);
+        job.save()?;
+        println!("Queued {queued} files, run the download command to download them again");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use apodex::archiving::html::ArchiveHtml;
+use apodex::archiving::Archive;
+use apodex::ApodEntry;
+use clap::Args;
+use std::path::PathBuf;
+
+/// Where entries are read from, defaults to the archive included in the binary
+#[derive(Args)]
+pub struct ArchiveArgs {
+    /// HTML archive file, as saved by the app
+    #[arg(long, conflicts_with = "sharded")]
+    pub archive: Option<PathBuf>,
+    /// Directory of a sharded HTML archive
+    #[arg(long)]
+    pub sharded: Option<PathBuf>,
+}
+
+impl ArchiveArgs {
+    pub fn load_html(&self) -> anyhow::Result<Archive<ArchiveHtml>> {
+        let archive = match (&self.archive, &self.sharded) {
+            (Some(path), _) => Archive::load(path)?,
+            (None, Some(dir)) => Archive::load_sharded(dir)?,
+            (None, None) => Archive::load_included_html_archive(),
+        };
+        Ok(archive)
+    }
+
+    /// Pages that fail to parse are reported and skipped
+    pub fn load_entries(&self) -> anyhow::Result<Archive<ApodEntry>> {
+        let html_archive = self.load_html()?;
+
+        let mut entries = Archive::default();
+        let mut failed = 0;
+        for (date, html) in html_archive.iter() {
+            match apodex::parsing::parse_html(*date, &html.html) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => failed += 1,
+            }
+        }
+
+        if failed > 0 {
+            eprintln!("Skipped {} pages that could not be parsed", failed);
+        }
+
+        Ok(entries)
+    }
+}
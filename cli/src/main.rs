@@ -0,0 +1,35 @@
+use clap::{Parser, Subcommand};
+
+mod commands;
+mod input;
+
+#[derive(Parser)]
+#[command(
+    name = "apodex",
+    about = "Tools for working with archived APOD entries"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Download media of many entries into a directory, resumable
+    Download(commands::download::DownloadArgs),
+    /// Write an RSS or Atom feed with full explanations
+    Feed(commands::feed::FeedArgs),
+    /// Render a browsable static website
+    Site(commands::site::SiteArgs),
+    /// Check cached media for corruption, optionally deleting or downloading it again
+    Verify(commands::verify::VerifyArgs),
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Download(args) => commands::download::run(args),
+        Command::Feed(args) => commands::feed::run(args),
+        Command::Site(args) => commands::site::run(args),
+        Command::Verify(args) => commands::verify::run(args),
+    }
+}
@@ -1,17 +1,29 @@
+use crate::date::range::ApodDateRange;
 use crate::date::ApodDate;
+#[cfg(feature = "include-html-archive")]
 use crate::INCLUDED_HTML_ARCHIVE;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::path::Path;
 use zstd::zstd_safe::CompressionLevel;
 
 pub mod html;
+pub mod patch;
+pub mod sharded;
+
+/// SHA-256 digest of encoded or compressed archive data
+pub type Checksum = [u8; 32];
 
 #[derive(Debug, thiserror::Error)]
 pub enum ArchiveError {
+    #[error("Checksum mismatch for '{0}'")]
+    ChecksumMismatch(String),
     #[error("Codec error: {0}")]
     Codec(#[from] bitcode::Error),
     #[error("IO error: {0}")]
     IO(#[from] std::io::Error),
+    #[error("Patch was made for a different base archive")]
+    PatchBaseMismatch,
 }
 
 #[derive(Debug, Clone)]
@@ -50,8 +62,14 @@ impl<E: ArchiveEntry> Archive<E> {
         self.entries.clear();
     }
 
+    pub fn extend(&mut self, other: Self) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Entries are sorted by date, so equal archives always encode to the same bytes
     pub fn encode(&self) -> Vec<u8> {
-        let entries: Vec<E> = self.entries.values().cloned().collect();
+        let mut entries: Vec<E> = self.entries.values().cloned().collect();
+        entries.sort_by_key(|entry| entry.date());
         bitcode::encode(&entries)
     }
 
@@ -84,6 +102,22 @@ impl<E: ArchiveEntry> Archive<E> {
         self.entries.contains_key(&date)
     }
 
+    /// Entries within the range, in order of their dates
+    pub fn iter_range(&self, range: ApodDateRange) -> impl Iterator<Item = &E> {
+        range.iter_all().filter_map(|date| self.entries.get(&date))
+    }
+
+    /// Entries sharing the month and day across all years, oldest first, see [`ApodDate::anniversaries`]
+    pub fn on_this_day(&self, month: u32, day: u32) -> Vec<&E> {
+        let Some(latest) = self.latest_date() else {
+            return Vec::new();
+        };
+        ApodDate::anniversaries(month, day, latest)
+            .into_iter()
+            .filter_map(|date| self.entries.get(&date))
+            .collect()
+    }
+
     pub fn get(&self, date: ApodDate) -> Option<&E> {
         self.entries.get(&date)
     }
@@ -92,6 +126,10 @@ impl<E: ArchiveEntry> Archive<E> {
         self.entries.iter()
     }
 
+    pub fn earliest_date(&self) -> Option<ApodDate> {
+        self.entries.keys().copied().min()
+    }
+
     pub fn latest_date(&self) -> Option<ApodDate> {
         self.entries.keys().copied().max()
     }
@@ -102,6 +140,10 @@ impl<E: ArchiveEntry> Archive<E> {
     }
 }
 
+pub fn checksum(data: &[u8]) -> Checksum {
+    Sha256::digest(data).into()
+}
+
 impl<E: ArchiveEntry> From<Vec<E>> for Archive<E> {
     fn from(value: Vec<E>) -> Self {
         let entries = value
@@ -1,7 +1,7 @@
 use crate::archiving::ArchiveEntry;
 use crate::date::ApodDate;
 
-#[derive(Debug, Clone, bitcode::Encode, bitcode::Decode)]
+#[derive(Debug, Clone, PartialEq, Eq, bitcode::Encode, bitcode::Decode)]
 pub struct ArchiveHtml {
     pub date: ApodDate,
     pub html: String,
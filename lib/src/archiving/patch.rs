@@ -0,0 +1,94 @@
+use crate::archiving::{checksum, Archive, ArchiveEntry, ArchiveError, Checksum};
+use std::path::Path;
+use zstd::zstd_safe::CompressionLevel;
+
+/// Entries that were added or changed relative to a base archive.
+/// The base is identified by the checksum of its encoded form, see [`Archive::checksum`].
+#[derive(Debug, Clone)]
+pub struct ArchivePatch<E: ArchiveEntry> {
+    pub base_checksum: Checksum,
+    pub entries: Vec<E>,
+}
+
+impl<E: ArchiveEntry> ArchivePatch<E> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn applies_to(&self, archive: &Archive<E>) -> bool {
+        archive.checksum() == self.base_checksum
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        bitcode::encode(&(self.base_checksum, self.entries.clone()))
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, ArchiveError> {
+        let (base_checksum, entries) = bitcode::decode(data)?;
+        Ok(Self {
+            base_checksum,
+            entries,
+        })
+    }
+
+    pub fn compress(&self, level: CompressionLevel) -> Vec<u8> {
+        zstd::encode_all(self.encode().as_slice(), level).unwrap()
+    }
+
+    pub fn decompress(data: &[u8]) -> Result<Self, ArchiveError> {
+        Self::decode(&zstd::decode_all(data)?)
+    }
+
+    pub fn save(
+        &self,
+        path: &Path,
+        compression_level: CompressionLevel,
+    ) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.compress(compression_level))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ArchiveError> {
+        Self::decompress(&std::fs::read(path)?)
+    }
+}
+
+impl<E: ArchiveEntry> Archive<E> {
+    pub fn checksum(&self) -> Checksum {
+        checksum(&self.encode())
+    }
+
+    /// Collects all entries of this archive that are missing or different in the base archive
+    pub fn make_patch(&self, base: &Self) -> ArchivePatch<E>
+    where
+        E: PartialEq,
+    {
+        let mut entries: Vec<E> = self
+            .iter()
+            .filter(|(date, entry)| base.get(**date) != Some(*entry))
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        entries.sort_by_key(|entry| entry.date());
+
+        ArchivePatch {
+            base_checksum: base.checksum(),
+            entries,
+        }
+    }
+
+    /// Fails without modifying the archive if it is not the base the patch was made for
+    pub fn apply_patch(&mut self, patch: &ArchivePatch<E>) -> Result<(), ArchiveError> {
+        if !patch.applies_to(self) {
+            return Err(ArchiveError::PatchBaseMismatch);
+        }
+
+        for entry in patch.entries.iter() {
+            self.push(entry.clone());
+        }
+
+        Ok(())
+    }
+}
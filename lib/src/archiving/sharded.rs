@@ -0,0 +1,139 @@
+use crate::archiving::{checksum, Archive, ArchiveEntry, ArchiveError, Checksum};
+use crate::date::ApodDate;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use zstd::zstd_safe::CompressionLevel;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.bin";
+
+/// Describes the per-year shards of an archive directory
+#[derive(Debug, Default, Clone, PartialEq, Eq, bitcode::Encode, bitcode::Decode)]
+pub struct ShardManifest {
+    pub shards: Vec<ShardInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, bitcode::Encode, bitcode::Decode)]
+pub struct ShardInfo {
+    pub year: i32,
+    pub first_date: ApodDate,
+    pub last_date: ApodDate,
+    pub entry_count: u32,
+    /// Checksum of the encoded shard before compression, see [`Archive::checksum`]
+    pub checksum: Checksum,
+}
+
+impl ShardInfo {
+    pub fn file_name(&self) -> String {
+        shard_file_name(self.year)
+    }
+}
+
+impl ShardManifest {
+    pub fn load(dir: &Path) -> Result<Self, ArchiveError> {
+        let data = std::fs::read(dir.join(MANIFEST_FILE_NAME))?;
+        Ok(bitcode::decode(&data)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), std::io::Error> {
+        std::fs::write(dir.join(MANIFEST_FILE_NAME), bitcode::encode(self))
+    }
+
+    pub fn get(&self, year: i32) -> Option<&ShardInfo> {
+        self.shards.iter().find(|shard| shard.year == year)
+    }
+
+    pub fn years(&self) -> impl Iterator<Item = i32> {
+        self.shards.iter().map(|shard| shard.year)
+    }
+
+    pub fn total_entries(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.entry_count as usize)
+            .sum()
+    }
+}
+
+impl<E: ArchiveEntry> Archive<E> {
+    pub fn split_by_year(&self) -> BTreeMap<i32, Self> {
+        let mut shards: BTreeMap<i32, Self> = BTreeMap::new();
+        for (date, entry) in self.iter() {
+            shards.entry(date.year()).or_default().push(entry.clone());
+        }
+        shards
+    }
+
+    /// Writes one compressed file per year plus a manifest into the given directory.
+    /// Shards whose content did not change since the last save are not rewritten.
+    pub fn save_sharded(
+        &self,
+        dir: &Path,
+        compression_level: CompressionLevel,
+    ) -> Result<ShardManifest, ArchiveError> {
+        std::fs::create_dir_all(dir)?;
+        let previous = ShardManifest::load(dir).unwrap_or_default();
+
+        let mut manifest = ShardManifest::default();
+        for (year, shard) in self.split_by_year() {
+            let (Some(first_date), Some(last_date)) = (shard.earliest_date(), shard.latest_date())
+            else {
+                continue;
+            };
+
+            let info = ShardInfo {
+                year,
+                first_date,
+                last_date,
+                entry_count: shard.len() as u32,
+                checksum: shard.checksum(),
+            };
+
+            // Compressing is the slow part, so it is skipped for unchanged shards
+            let path = dir.join(info.file_name());
+            if previous.get(year) != Some(&info) || !path.exists() {
+                std::fs::write(path, shard.compress(compression_level))?;
+            }
+            manifest.shards.push(info);
+        }
+
+        for stale in previous.shards.iter() {
+            if manifest.get(stale.year).is_none() {
+                let _ = std::fs::remove_file(dir.join(stale.file_name()));
+            }
+        }
+
+        manifest.save(dir)?;
+        Ok(manifest)
+    }
+
+    pub fn load_sharded(dir: &Path) -> Result<Self, ArchiveError> {
+        Self::load_sharded_years(dir, i32::MIN..=i32::MAX)
+    }
+
+    pub fn load_sharded_years(
+        dir: &Path,
+        years: RangeInclusive<i32>,
+    ) -> Result<Self, ArchiveError> {
+        let manifest = ShardManifest::load(dir)?;
+
+        let mut archive = Self::default();
+        for info in manifest
+            .shards
+            .iter()
+            .filter(|shard| years.contains(&shard.year))
+        {
+            let data = zstd::decode_all(std::fs::read(dir.join(info.file_name()))?.as_slice())?;
+            if checksum(&data) != info.checksum {
+                return Err(ArchiveError::ChecksumMismatch(info.file_name()));
+            }
+            archive.extend(Self::decode(&data)?);
+        }
+
+        Ok(archive)
+    }
+}
+
+pub fn shard_file_name(year: i32) -> String {
+    format!("{year}.bin")
+}
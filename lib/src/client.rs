@@ -1,5 +1,6 @@
 use crate::date::ApodDate;
-use crate::media::{MediaEntry, MediaType};
+use crate::media::{MediaEntry, MediaType, MediaVariant};
+use crate::parsing::media_url::parse_oembed_thumbnail;
 use crate::{ApodEntry, APOD_BASE_URL};
 
 #[cfg(feature = "reqwest-client")]
@@ -22,7 +23,7 @@ pub trait ApodClient {
     ) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>>;
 
     async fn fetch_page(&self, date: ApodDate) -> Result<Option<String>, ClientError> {
-        let url = format!("{APOD_BASE_URL}/ap{}.html", date.format("%y%m%d"));
+        let url = format!("{APOD_BASE_URL}/ap{}.html", date.page_code());
 
         let Some(bytes) = self
             .fetch(&url)
@@ -35,8 +36,21 @@ pub trait ApodClient {
         Ok(Some(String::from_utf8_lossy(bytes.as_slice()).into_owned()))
     }
 
+    /// Fetches the media in the highest quality available
     async fn fetch_media(&self, entry: &ApodEntry) -> Result<Option<MediaEntry>, ClientError> {
-        let Some(url) = entry.media.highest_quality() else {
+        self.fetch_media_variant(entry, MediaVariant::Hd).await
+    }
+
+    /// Only full size variants and posters can be fetched, others resolve to None
+    async fn fetch_media_variant(
+        &self,
+        entry: &ApodEntry,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, ClientError> {
+        if variant == MediaVariant::Poster {
+            return self.fetch_poster(entry).await;
+        }
+        let Some(url) = entry.media.variant_url(variant) else {
             return Ok(None);
         };
 
@@ -49,8 +63,46 @@ pub trait ApodClient {
         };
 
         Ok(Some(MediaEntry {
-            media_type: MediaType::ImagePNG,
+            media_type: MediaType::sniff(&bytes).unwrap_or(MediaType::Other),
             data: bytes,
         }))
     }
+
+    /// Fetches the still image of an embedded video from its host,
+    /// None for other media and if the host has none
+    async fn fetch_poster(&self, entry: &ApodEntry) -> Result<Option<MediaEntry>, ClientError> {
+        let Some(video) = entry.media.embedded_video() else {
+            return Ok(None);
+        };
+
+        let mut urls = video.poster_urls();
+        if let Some(url) = video.oembed_url() {
+            let response = self.fetch(&url).await.map_err(|e| ClientError::Fetch {
+                url: url.clone(),
+                source: e,
+            })?;
+            if let Some(bytes) = response {
+                urls.extend(parse_oembed_thumbnail(&String::from_utf8_lossy(&bytes)));
+            }
+        }
+
+        for url in urls {
+            let Some(bytes) = self.fetch(&url).await.map_err(|e| ClientError::Fetch {
+                url: url.clone(),
+                source: e,
+            })?
+            else {
+                continue;
+            };
+            if let Some(media_type) = MediaType::sniff(&bytes)
+                && media_type.is_image()
+            {
+                return Ok(Some(MediaEntry {
+                    media_type,
+                    data: bytes,
+                }));
+            }
+        }
+        Ok(None)
+    }
 }
@@ -1,5 +1,35 @@
-use chrono::{Local, NaiveDate};
+use crate::date::missing::MissingDateRegistry;
+use crate::date::range::ApodDateRange;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
 use std::fmt::Display;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+pub mod iso;
+pub mod missing;
+pub mod range;
+
+/// How the date of the latest available APOD is determined
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CurrentDateMode {
+    /// The current date in US Eastern time, where new entries are published at midnight
+    #[default]
+    Publication,
+    /// The current date of the local system clock
+    Local,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DateParseError {
+    #[error(
+        "Invalid date '{0}', expected YYYY-MM-DD, YYMMDD, an APOD page URL, today, yesterday or -Nd"
+    )]
+    InvalidDate(String),
+    #[error("Invalid date range '{0}', expected START..END, YYYY or YYYY-MM")]
+    InvalidRange(String),
+}
 
 /// Counting days since 1995-6-16, where APOD starts
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -10,7 +40,7 @@ pub struct ApodDate(i32);
 impl ApodDate {
     pub const START: Self = Self(0);
     pub const CHRONO_START: NaiveDate = NaiveDate::from_ymd_opt(1995, 6, 16).unwrap();
-    /// Dates that are known to have no APOD entry
+    /// Dates that are known to have no APOD entry, seeding the [`MissingDateRegistry`]
     pub const KNOWN_MISSING_DATES: [Self; 4] = [
         Self::from_ymd_unsafe(1995, 6, 17),
         Self::from_ymd_unsafe(1995, 6, 18),
@@ -18,10 +48,18 @@ impl ApodDate {
         Self::from_ymd_unsafe(2020, 6, 10),
     ];
 
-    pub fn iter_till_today() -> impl Iterator<Item = Self> {
-        (0..=Self::today().days()).filter_map(|days| {
+    /// APOD rolls over at midnight in this timezone
+    pub const PUBLICATION_TIMEZONE: Tz = chrono_tz::America::New_York;
+
+    /// All dates up to the current one that can have an entry, skipping confirmed missing dates
+    pub fn iter_till_today(
+        mode: CurrentDateMode,
+        registry: &MissingDateRegistry,
+    ) -> impl Iterator<Item = Self> + use<> {
+        let missing = registry.confirmed();
+        (0..=Self::current(mode).days()).filter_map(move |days| {
             let date = Self(days);
-            if Self::KNOWN_MISSING_DATES.contains(&date) {
+            if missing.binary_search(&date).is_ok() {
                 None
             } else {
                 Some(date)
@@ -29,35 +67,137 @@ impl ApodDate {
         })
     }
 
+    /// The local date, which can be ahead of or behind the latest published entry
     pub fn today() -> Self {
         Self::from(Local::now().date_naive())
     }
 
-    pub fn total_apod_days() -> u32 {
-        (Self::today().days() as u32)
+    /// The current date in the publication timezone, i.e. of the latest published entry
+    pub fn publication_today() -> Self {
+        Self::from(
+            Utc::now()
+                .with_timezone(&Self::PUBLICATION_TIMEZONE)
+                .date_naive(),
+        )
+    }
+
+    /// The latest date with a possible entry, according to the mode
+    pub fn current(mode: CurrentDateMode) -> Self {
+        match mode {
+            CurrentDateMode::Publication => Self::publication_today(),
+            CurrentDateMode::Local => Self::today(),
+        }
+    }
+
+    pub fn total_apod_days(mode: CurrentDateMode, registry: &MissingDateRegistry) -> u32 {
+        let current = Self::current(mode);
+        let missing = registry
+            .confirmed()
+            .into_iter()
+            .filter(|date| *date <= current)
+            .count();
+        (current.days() as u32)
             .saturating_add(1)
-            .saturating_sub(Self::KNOWN_MISSING_DATES.len() as u32)
+            .saturating_sub(missing as u32)
     }
 
     pub fn parse_from_str(date: &str, fmt: &str) -> Option<Self> {
         NaiveDate::parse_from_str(date, fmt).ok().map(Self::from)
     }
 
+    /// Strictly `YYYY-MM-DD`, for stored data where relative dates make no sense
+    pub fn from_iso(date: &str) -> Option<Self> {
+        Self::parse_from_str(date, "%Y-%m-%d")
+    }
+
+    /// The `YYMMDD` code of APOD page names like `ap050304.html`
+    pub fn from_page_code(code: &str) -> Option<Self> {
+        if code.len() != 6 || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        Self::parse_from_str(code, "%y%m%d")
+    }
+
+    pub fn page_code(&self) -> String {
+        self.format("%y%m%d")
+    }
+
     pub fn days(&self) -> i32 {
         self.0
     }
 
+    /// Inverse of [`ApodDate::days`]
+    pub fn from_days(days: i32) -> Self {
+        Self(days)
+    }
+
+    pub fn year(&self) -> i32 {
+        NaiveDate::from(*self).year()
+    }
+
+    pub fn month(&self) -> u32 {
+        NaiveDate::from(*self).month()
+    }
+
+    pub fn day(&self) -> u32 {
+        NaiveDate::from(*self).day()
+    }
+
+    /// The same month and day in another year, Feb 29 falls back to Feb 28 in common years
+    pub fn with_year(&self, year: i32) -> Option<Self> {
+        Self::from_month_day(year, self.month(), self.day())
+    }
+
+    fn from_month_day(year: i32, month: u32, day: u32) -> Option<Self> {
+        Self::from_ymd(year, month, day).or_else(|| {
+            if month == 2 && day == 29 {
+                Self::from_ymd(year, 2, 28)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The given month and day in every year of APOD up to `end`, oldest first.
+    /// Feb 29 falls back to Feb 28 in common years.
+    pub fn anniversaries(month: u32, day: u32, end: ApodDate) -> Vec<Self> {
+        let range = ApodDateRange::new(Self::START, end);
+        (range.start().year()..=range.end().year())
+            .filter_map(|year| Self::from_month_day(year, month, day))
+            .filter(|date| range.contains(*date))
+            .collect()
+    }
+
+    pub fn add_days(&self, days: i32) -> Self {
+        Self(self.0.saturating_add(days))
+    }
+
     pub fn format(&self, fmt: &str) -> String {
         NaiveDate::from(*self).format(fmt).to_string()
     }
 
+    /// Start of the day in UTC, e.g. `2005-03-04T00:00:00Z`
+    pub fn to_rfc3339(&self) -> String {
+        self.start_of_day_utc()
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    /// Start of the day in UTC, e.g. `Fri, 4 Mar 2005 00:00:00 +0000`
+    pub fn to_rfc2822(&self) -> String {
+        self.start_of_day_utc().to_rfc2822()
+    }
+
+    fn start_of_day_utc(&self) -> DateTime<Utc> {
+        NaiveDate::from(*self).and_time(NaiveTime::MIN).and_utc()
+    }
+
     pub fn link(&self) -> Option<String> {
         if self.days() < 0 {
             return None;
         }
         Some(format!(
             "https://apod.nasa.gov/apod/ap{}.html",
-            self.format("%y%m%d")
+            self.page_code()
         ))
     }
 
@@ -95,3 +235,42 @@ impl Display for ApodDate {
         write!(f, "{}", self.format("%Y-%m-%d"))
     }
 }
+
+/// Accepts ISO dates, APOD page codes and URLs like `ap050304.html`,
+/// as well as `today`, `yesterday` and `-Nd` relative to the default [`CurrentDateMode`]
+impl FromStr for ApodDate {
+    type Err = DateParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        let invalid = || DateParseError::InvalidDate(s.to_string());
+
+        let current = || Self::current(CurrentDateMode::default());
+        match value.to_ascii_lowercase().as_str() {
+            "today" => return Ok(current()),
+            "yesterday" => return Ok(current().add_days(-1)),
+            _ => {}
+        }
+
+        if let Some(days) = value
+            .strip_prefix('-')
+            .and_then(|rest| rest.strip_suffix('d'))
+        {
+            let days: u16 = days.parse().map_err(|_| invalid())?;
+            return Ok(current().add_days(-(days as i32)));
+        }
+
+        if value.ends_with(".html") {
+            let page = value.rsplit('/').next().unwrap_or(value);
+            return page
+                .strip_prefix("ap")
+                .and_then(|page| page.strip_suffix(".html"))
+                .and_then(Self::from_page_code)
+                .ok_or_else(invalid);
+        }
+
+        Self::from_iso(value)
+            .or_else(|| Self::from_page_code(value))
+            .ok_or_else(invalid)
+    }
+}
@@ -0,0 +1,69 @@
+//! Human readable serde for dates as `YYYY-MM-DD` strings, instead of the raw day counter.
+//!
+//! Opt in per field with `#[serde(with = "apodex::date::iso")]`.
+use crate::date::ApodDate;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(date: &ApodDate, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(date)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ApodDate, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    ApodDate::from_iso(&value).ok_or_else(|| D::Error::custom(format!("invalid date '{value}'")))
+}
+
+/// For `Option<ApodDate>` fields
+pub mod option {
+    use crate::date::ApodDate;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<ApodDate>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.collect_str(date),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<ApodDate>, D::Error> {
+        let Some(value) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        ApodDate::from_iso(&value)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("invalid date '{value}'")))
+    }
+}
+
+/// For [`ApodDateRange`](crate::date::range::ApodDateRange) fields, as `START..END`
+pub mod range {
+    use crate::date::range::ApodDateRange;
+    use crate::date::ApodDate;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        range: &ApodDateRange,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(range)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ApodDateRange, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let (start, end) = value
+            .split_once("..")
+            .and_then(|(start, end)| Some((ApodDate::from_iso(start)?, ApodDate::from_iso(end)?)))
+            .ok_or_else(|| D::Error::custom(format!("invalid date range '{value}'")))?;
+        Ok(ApodDateRange::new(start, end))
+    }
+}
@@ -0,0 +1,179 @@
+use crate::date::ApodDate;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MissingReason {
+    /// Part of [`ApodDate::KNOWN_MISSING_DATES`], never verified again
+    Known,
+    /// The page returned a 404 when it was scraped
+    NotFound,
+}
+
+impl Display for MissingReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MissingReason::Known => write!(f, "Known"),
+            MissingReason::NotFound => write!(f, "Not found"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MissingDate {
+    pub reason: MissingReason,
+    /// Unix timestamp in seconds of when the date was last confirmed to be missing
+    pub confirmed_at: i64,
+}
+
+/// Dates that are confirmed to have no APOD entry,
+/// passed to everything that iterates or counts dates up to the current one
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MissingDateRegistry {
+    dates: BTreeMap<ApodDate, MissingDate>,
+    /// Days after which a date that was not found has to be verified again, never if `None`
+    reverify_after_days: Option<u32>,
+}
+
+impl Default for MissingDateRegistry {
+    /// Seeded with the known missing dates
+    fn default() -> Self {
+        let dates = ApodDate::KNOWN_MISSING_DATES
+            .iter()
+            .map(|date| {
+                let missing = MissingDate {
+                    reason: MissingReason::Known,
+                    confirmed_at: 0,
+                };
+                (*date, missing)
+            })
+            .collect();
+
+        Self {
+            dates,
+            reverify_after_days: Some(30),
+        }
+    }
+}
+
+impl MissingDateRegistry {
+    /// Records a date as missing as of now, or refreshes its confirmation
+    pub fn record(&mut self, date: ApodDate, reason: MissingReason) {
+        let confirmed_at = Utc::now().timestamp();
+        let missing = self.dates.entry(date).or_insert(MissingDate {
+            reason,
+            confirmed_at,
+        });
+        if missing.reason != MissingReason::Known {
+            missing.reason = reason;
+        }
+        missing.confirmed_at = confirmed_at;
+    }
+
+    /// Removes a date that turned out to have an entry after all, returns whether it was registered.
+    /// Known missing dates are kept.
+    pub fn remove(&mut self, date: ApodDate) -> bool {
+        match self.dates.get(&date) {
+            Some(missing) if missing.reason != MissingReason::Known => {
+                self.dates.remove(&date);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, date: ApodDate) -> Option<&MissingDate> {
+        self.dates.get(&date)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ApodDate, &MissingDate)> {
+        self.dates.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dates.is_empty()
+    }
+
+    pub fn reverify_after_days(&self) -> Option<u32> {
+        self.reverify_after_days
+    }
+
+    pub fn set_reverify_after_days(&mut self, days: Option<u32>) {
+        self.reverify_after_days = days;
+    }
+
+    /// Whether the date has to be fetched again to confirm it is still missing
+    pub fn needs_verification(&self, date: ApodDate) -> bool {
+        self.dates
+            .get(&date)
+            .is_some_and(|missing| self.is_expired(missing, Utc::now().timestamp()))
+    }
+
+    /// Registered and not due for verification, these dates are skipped when iterating or counting
+    pub fn is_missing(&self, date: ApodDate) -> bool {
+        self.dates
+            .get(&date)
+            .is_some_and(|missing| !self.is_expired(missing, Utc::now().timestamp()))
+    }
+
+    /// All dates that are currently confirmed missing, in order
+    pub fn confirmed(&self) -> Vec<ApodDate> {
+        let now = Utc::now().timestamp();
+        self.dates
+            .iter()
+            .filter(|(_, missing)| !self.is_expired(missing, now))
+            .map(|(date, _)| *date)
+            .collect()
+    }
+
+    /// All dates whose confirmation is older than the re-verification period, in order
+    pub fn due_for_verification(&self) -> Vec<ApodDate> {
+        let now = Utc::now().timestamp();
+        self.dates
+            .iter()
+            .filter(|(_, missing)| self.is_expired(missing, now))
+            .map(|(date, _)| *date)
+            .collect()
+    }
+
+    fn is_expired(&self, missing: &MissingDate, now: i64) -> bool {
+        if missing.reason == MissingReason::Known {
+            return false;
+        }
+        match self.reverify_after_days {
+            Some(days) => now.saturating_sub(missing.confirmed_at) >= days as i64 * 86_400,
+            None => false,
+        }
+    }
+}
+
+#[cfg(feature = "archiving")]
+impl MissingDateRegistry {
+    pub fn save(&self, path: &std::path::Path) -> Result<(), std::io::Error> {
+        std::fs::write(path, bitcode::encode(self))
+    }
+
+    /// The known missing dates are always kept
+    pub fn load(path: &std::path::Path) -> Result<Self, crate::archiving::ArchiveError> {
+        let mut registry: Self = bitcode::decode(&std::fs::read(path)?)?;
+        registry.seed_known();
+        Ok(registry)
+    }
+
+    fn seed_known(&mut self) {
+        for (date, missing) in Self::default().dates {
+            self.dates.entry(date).or_insert(missing);
+        }
+    }
+}
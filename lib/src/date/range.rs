@@ -0,0 +1,167 @@
+use crate::date::missing::MissingDateRegistry;
+use crate::date::{ApodDate, CurrentDateMode, DateParseError};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Dates from start to end, both inclusive.
+/// Not bitcode encodable, decoding could not keep the start before the end.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "RawRange"))]
+pub struct ApodDateRange {
+    start: ApodDate,
+    end: ApodDate,
+}
+
+/// Deserialized as is, then ordered by [`ApodDateRange::new`]
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawRange {
+    start: ApodDate,
+    end: ApodDate,
+}
+
+#[cfg(feature = "serde")]
+impl From<RawRange> for ApodDateRange {
+    fn from(raw: RawRange) -> Self {
+        Self::new(raw.start, raw.end)
+    }
+}
+
+impl ApodDateRange {
+    /// The dates can be given in any order
+    pub fn new(a: ApodDate, b: ApodDate) -> Self {
+        Self {
+            start: a.min(b),
+            end: a.max(b),
+        }
+    }
+
+    pub fn single(date: ApodDate) -> Self {
+        Self::new(date, date)
+    }
+
+    /// From the first APOD up to the current date
+    pub fn till_current(mode: CurrentDateMode) -> Self {
+        Self::new(ApodDate::START, ApodDate::current(mode))
+    }
+
+    /// The whole year, starting at the first APOD in 1995
+    pub fn year(year: i32) -> Option<Self> {
+        let start = ApodDate::from_ymd(year, 1, 1)?;
+        let end = ApodDate::from_ymd(year, 12, 31)?;
+        Self::clamped(start, end)
+    }
+
+    pub fn month(year: i32, month: u32) -> Option<Self> {
+        let start = ApodDate::from_ymd(year, month, 1)?;
+        let next_month = if month == 12 {
+            ApodDate::from_ymd(year + 1, 1, 1)?
+        } else {
+            ApodDate::from_ymd(year, month + 1, 1)?
+        };
+        Self::clamped(start, next_month.add_days(-1))
+    }
+
+    fn clamped(start: ApodDate, end: ApodDate) -> Option<Self> {
+        if end < ApodDate::START {
+            return None;
+        }
+        Some(Self::new(start.max(ApodDate::START), end))
+    }
+
+    pub fn start(&self) -> ApodDate {
+        self.start
+    }
+
+    pub fn end(&self) -> ApodDate {
+        self.end
+    }
+
+    pub fn contains(&self, date: ApodDate) -> bool {
+        date >= self.start && date <= self.end
+    }
+
+    /// Number of days in the range, including confirmed missing dates
+    pub fn days(&self) -> u32 {
+        self.end
+            .days()
+            .abs_diff(self.start.days())
+            .saturating_add(1)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start <= end).then_some(Self { start, end })
+    }
+
+    /// All dates that can have an entry, skipping confirmed missing dates
+    pub fn iter(&self, registry: &MissingDateRegistry) -> impl Iterator<Item = ApodDate> + use<> {
+        let missing = registry.confirmed();
+        self.iter_all()
+            .filter(move |date| missing.binary_search(date).is_err())
+    }
+
+    /// Every date of the range, including confirmed missing dates
+    pub fn iter_all(&self) -> impl Iterator<Item = ApodDate> + use<> {
+        (self.start.days()..=self.end.days()).map(ApodDate)
+    }
+
+    /// One range per calendar year, the first and last can be partial
+    pub fn split_by_year(&self) -> Vec<Self> {
+        (self.start.year()..=self.end.year())
+            .filter_map(Self::year)
+            .filter_map(|year| year.intersection(self))
+            .collect()
+    }
+
+    /// One range per calendar month, the first and last can be partial
+    pub fn split_by_month(&self) -> Vec<Self> {
+        self.split_by_year()
+            .into_iter()
+            .flat_map(|year| {
+                let year_number = year.start.year();
+                (year.start.month()..=year.end.month())
+                    .filter_map(move |month| Self::month(year_number, month))
+                    .filter_map(move |month| month.intersection(&year))
+            })
+            .collect()
+    }
+}
+
+impl Display for ApodDateRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// Accepts `START..END` with any date [`ApodDate`] can parse, a year `YYYY`,
+/// a month `YYYY-MM` or a single date
+impl FromStr for ApodDateRange {
+    type Err = DateParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        let invalid = || DateParseError::InvalidRange(s.to_string());
+
+        if let Some((start, end)) = value.split_once("..") {
+            return Ok(Self::new(start.parse()?, end.parse()?));
+        }
+
+        if value.len() == 4 {
+            let year = value.parse().map_err(|_| invalid())?;
+            return Self::year(year).ok_or_else(invalid);
+        }
+
+        if value.len() == 7
+            && let Some((year, month)) = value.split_once('-')
+        {
+            let year = year.parse().map_err(|_| invalid())?;
+            let month = month.parse().map_err(|_| invalid())?;
+            return Self::month(year, month).ok_or_else(invalid);
+        }
+
+        value.parse().map(Self::single)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::archiving::Archive;
+use crate::exporting::record::EntryRecord;
+use crate::ApodEntry;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+mod csv;
+#[cfg(feature = "epub")]
+pub mod epub;
+pub mod feed;
+mod json;
+pub mod markdown;
+mod markup;
+pub mod record;
+#[cfg(feature = "site")]
+pub mod site;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("CSV error: {0}")]
+    Csv(#[from] ::csv::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "epub")]
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum ExportFormat {
+    /// A single pretty-printed JSON array
+    #[default]
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ExportColumn {
+    Date,
+    Title,
+    Explanation,
+    MediaUrl,
+    HdUrl,
+    MediaKind,
+    Warnings,
+}
+
+impl ExportColumn {
+    pub const ALL: [Self; 7] = [
+        Self::Date,
+        Self::Title,
+        Self::Explanation,
+        Self::MediaUrl,
+        Self::HdUrl,
+        Self::MediaKind,
+        Self::Warnings,
+    ];
+
+    /// Field name used as JSON key and CSV header
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportColumn::Date => "date",
+            ExportColumn::Title => "title",
+            ExportColumn::Explanation => "explanation",
+            ExportColumn::MediaUrl => "media_url",
+            ExportColumn::HdUrl => "hd_url",
+            ExportColumn::MediaKind => "media_kind",
+            ExportColumn::Warnings => "warnings",
+        }
+    }
+}
+
+impl Display for ExportColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportColumn::Date => write!(f, "Date"),
+            ExportColumn::Title => write!(f, "Title"),
+            ExportColumn::Explanation => write!(f, "Explanation"),
+            ExportColumn::MediaUrl => write!(f, "Media URL"),
+            ExportColumn::HdUrl => write!(f, "HD URL"),
+            ExportColumn::MediaKind => write!(f, "Media kind"),
+            ExportColumn::Warnings => write!(f, "Warnings"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub columns: Vec<ExportColumn>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::default(),
+            columns: ExportColumn::ALL.to_vec(),
+        }
+    }
+}
+
+/// Writes all entries sorted by date, only including the selected columns
+pub fn export_entries(
+    archive: &Archive<ApodEntry>,
+    options: &ExportOptions,
+    writer: impl Write,
+) -> Result<(), ExportError> {
+    let mut entries: Vec<&ApodEntry> = archive.iter().map(|(_, entry)| entry).collect();
+    entries.sort_by_key(|entry| entry.date);
+
+    let records = entries
+        .into_iter()
+        .map(|entry| EntryRecord::from_entry(entry, &options.columns));
+
+    match options.format {
+        ExportFormat::Json => json::write_json(records, writer),
+        ExportFormat::Ndjson => json::write_ndjson(records, writer),
+        ExportFormat::Csv => csv::write_csv(records, &options.columns, writer),
+    }
+}
+
+pub fn export_entries_to_file(
+    archive: &Archive<ApodEntry>,
+    options: &ExportOptions,
+    path: &Path,
+) -> Result<(), ExportError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    export_entries(archive, options, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use crate::exporting::record::EntryRecord;
+use crate::exporting::{ExportColumn, ExportError};
+use std::io::Write;
+
+pub fn write_csv(
+    records: impl Iterator<Item = EntryRecord>,
+    columns: &[ExportColumn],
+    writer: impl Write,
+) -> Result<(), ExportError> {
+    let mut csv_writer = ::csv::Writer::from_writer(writer);
+    csv_writer.write_record(columns.iter().map(|column| column.name()))?;
+
+    for record in records {
+        csv_writer.write_record(columns.iter().map(|column| record.field(*column)))?;
+    }
+
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,324 @@
+use crate::archiving::Archive;
+use crate::date::range::ApodDateRange;
+use crate::date::ApodDate;
+use crate::exporting::markup::escape_xml;
+use crate::exporting::ExportError;
+use crate::media::{MediaCache, MediaType};
+use crate::ApodEntry;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE: &str = r#"body { font-family: serif; line-height: 1.4; }
+h1 { font-size: 1.4em; }
+.date { font-style: italic; }
+.media { text-align: center; margin: 1em 0; }
+.media img { max-width: 100%; }
+.credits { font-size: 0.9em; }
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EpubOptions {
+    pub title: String,
+    pub range: ApodDateRange,
+    /// Language tag of the book metadata
+    pub language: String,
+}
+
+impl EpubOptions {
+    /// Covers a whole year, titled e.g. "APOD 2005"
+    pub fn year(year: i32) -> Option<Self> {
+        Some(Self {
+            title: format!("APOD {}", year),
+            range: ApodDateRange::year(year)?,
+            language: "en".to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EpubReport {
+    pub entries: usize,
+    pub images: usize,
+    /// Entries in the range that are shown without their media
+    pub missing_media: Vec<ApodDate>,
+}
+
+/// Files of a single entry inside the book, relative to the package document
+struct EpubItem<'a> {
+    entry: &'a ApodEntry,
+    page: String,
+    image: Option<(String, &'static str)>,
+}
+
+/// Writes all entries of the date range into an EPUB 3 book with one chapter per month.
+///
+/// Images are taken from the media cache as they are stored, entries without cached media
+/// only link their original media. Credits are optional and keyed by date.
+pub fn export_epub(
+    archive: &Archive<ApodEntry>,
+    credits: &HashMap<ApodDate, String>,
+    media: Option<&dyn MediaCache>,
+    options: &EpubOptions,
+    writer: impl Write + Seek,
+) -> Result<EpubReport, ExportError> {
+    let entries: Vec<&ApodEntry> = archive.iter_range(options.range).collect();
+
+    let mut zip = ZipWriter::new(writer);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype has to be the first and uncompressed entry of the archive
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(STYLE.as_bytes())?;
+
+    let mut report = EpubReport::default();
+    let mut months: BTreeMap<(i32, String), Vec<EpubItem>> = BTreeMap::new();
+    for entry in entries {
+        let page = format!("entries/{}.xhtml", entry.date);
+
+        let image = match media.map(|cache| cache.get(entry.date)) {
+            Some(Ok(Some(media_entry))) => match MediaType::sniff(&media_entry.data) {
+                Some(media_type) => {
+                    let path = format!("images/{}.{}", entry.date, media_type.extension());
+                    // Images are already compressed
+                    zip.start_file(format!("OEBPS/{}", path), stored)?;
+                    zip.write_all(&media_entry.data)?;
+                    report.images += 1;
+                    Some((path, media_type.mime_type()))
+                }
+                None => None,
+            },
+            _ => None,
+        };
+        if image.is_none() {
+            report.missing_media.push(entry.date);
+        }
+
+        let xhtml = render_entry(entry, credits.get(&entry.date), image.as_ref());
+        zip.start_file(format!("OEBPS/{}", page), deflated)?;
+        zip.write_all(xhtml.as_bytes())?;
+        report.entries += 1;
+
+        months
+            .entry((entry.date.year(), entry.date.format("%m")))
+            .or_default()
+            .push(EpubItem { entry, page, image });
+    }
+
+    for ((year, month), items) in months.iter() {
+        let xhtml = render_month(&month_title(*year, month), items);
+        zip.start_file(format!("OEBPS/{}", month_page(*year, month)), deflated)?;
+        zip.write_all(xhtml.as_bytes())?;
+    }
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(render_nav(options, &months).as_bytes())?;
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(render_package(options, &months).as_bytes())?;
+
+    zip.finish()?;
+    Ok(report)
+}
+
+pub fn export_epub_to_file(
+    archive: &Archive<ApodEntry>,
+    credits: &HashMap<ApodDate, String>,
+    media: Option<&dyn MediaCache>,
+    options: &EpubOptions,
+    path: &Path,
+) -> Result<EpubReport, ExportError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let report = export_epub(archive, credits, media, options, &mut writer)?;
+    writer.flush()?;
+    Ok(report)
+}
+
+fn month_page(year: i32, month: &str) -> String {
+    format!("months/{}-{}.xhtml", year, month)
+}
+
+fn month_title(year: i32, month: &str) -> String {
+    let name = month
+        .parse()
+        .ok()
+        .and_then(|month| ApodDate::from_ymd(2000, month, 1))
+        .map(|date| date.format("%B"))
+        .unwrap_or_default();
+    format!("{} {}", name, year)
+}
+
+fn render_xhtml(title: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{}</title>
+<link rel="stylesheet" type="text/css" href="{}"/>
+</head>
+<body>
+{}
+</body>
+</html>
+"#,
+        escape_xml(title),
+        stylesheet,
+        body
+    )
+}
+
+fn render_entry(
+    entry: &ApodEntry,
+    credits: Option<&String>,
+    image: Option<&(String, &'static str)>,
+) -> String {
+    let mut body = format!(
+        "<section epub:type=\"chapter\">\n<h1>{}</h1>\n<p class=\"date\">{}</p>\n",
+        escape_xml(&entry.title),
+        entry.date.format("%B %-d, %Y")
+    );
+
+    match (image, entry.media.highest_quality()) {
+        (Some((path, _)), _) => body.push_str(&format!(
+            "<div class=\"media\"><img src=\"../{}\" alt=\"{}\"/></div>\n",
+            escape_xml(path),
+            escape_xml(&entry.title)
+        )),
+        (None, Some(url)) => body.push_str(&format!(
+            "<p class=\"media\"><a href=\"{}\">View media online</a></p>\n",
+            escape_xml(url)
+        )),
+        (None, None) => {}
+    }
+
+    body.push_str(&format!("<p>{}</p>\n", escape_xml(&entry.explanation)));
+    if let Some(credits) = credits {
+        body.push_str(&format!(
+            "<p class=\"credits\">Credit: {}</p>\n",
+            escape_xml(credits)
+        ));
+    }
+    body.push_str("</section>");
+
+    render_xhtml(&entry.title, "../style.css", &body)
+}
+
+fn render_month(title: &str, items: &[EpubItem]) -> String {
+    let mut body = format!(
+        "<section epub:type=\"part\">\n<h1>{}</h1>\n<ol>\n",
+        escape_xml(title)
+    );
+    for item in items {
+        body.push_str(&format!(
+            "<li><a href=\"../{}\">{}</a> ({})</li>\n",
+            item.page,
+            escape_xml(&item.entry.title),
+            item.entry.date
+        ));
+    }
+    body.push_str("</ol>\n</section>");
+
+    render_xhtml(title, "../style.css", &body)
+}
+
+fn render_nav(options: &EpubOptions, months: &BTreeMap<(i32, String), Vec<EpubItem>>) -> String {
+    let mut body = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n",
+        escape_xml(&options.title)
+    );
+    for ((year, month), items) in months.iter() {
+        body.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>\n<ol>\n",
+            month_page(*year, month),
+            month_title(*year, month)
+        ));
+        for item in items {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                item.page,
+                escape_xml(&item.entry.title)
+            ));
+        }
+        body.push_str("</ol>\n</li>\n");
+    }
+    body.push_str("</ol>\n</nav>");
+
+    render_xhtml(&options.title, "style.css", &body)
+}
+
+fn render_package(
+    options: &EpubOptions,
+    months: &BTreeMap<(i32, String), Vec<EpubItem>>,
+) -> String {
+    let mut manifest = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n    <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+
+    for ((year, month), items) in months.iter() {
+        let id = format!("month-{}-{}", year, month);
+        manifest.push_str(&format!(
+            "    <item id=\"{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            id,
+            month_page(*year, month)
+        ));
+        spine.push_str(&format!("    <itemref idref=\"{}\"/>\n", id));
+
+        for item in items {
+            let id = format!("entry-{}", item.entry.date);
+            manifest.push_str(&format!(
+                "    <item id=\"{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+                id, item.page
+            ));
+            spine.push_str(&format!("    <itemref idref=\"{}\"/>\n", id));
+
+            if let Some((path, media_type)) = &item.image {
+                manifest.push_str(&format!(
+                    "    <item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                    item.entry.date, path, media_type
+                ));
+            }
+        }
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:apodex:{start}:{end}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>{language}</dc:language>
+    <dc:creator>NASA Astronomy Picture of the Day</dc:creator>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+        start = options.range.start(),
+        end = options.range.end(),
+        title = escape_xml(&options.title),
+        language = escape_xml(&options.language),
+        modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest = manifest,
+        spine = spine,
+    )
+}
@@ -0,0 +1,301 @@
+use crate::archiving::Archive;
+use crate::date::range::ApodDateRange;
+use crate::date::ApodDate;
+use crate::exporting::markup::escape_xml;
+use crate::exporting::ExportError;
+use crate::media::{MediaCache, MediaVariant};
+use crate::{ApodEntry, APOD_BASE_URL};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum FeedFormat {
+    #[default]
+    Rss,
+    Atom,
+}
+
+impl FeedFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "rss",
+            FeedFormat::Atom => "atom",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum FeedSelection {
+    /// The latest entries of the archive
+    Latest(usize),
+    /// All entries of the range
+    Range(ApodDateRange),
+}
+
+impl Default for FeedSelection {
+    fn default() -> Self {
+        Self::Latest(30)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FeedOptions {
+    pub format: FeedFormat,
+    pub selection: FeedSelection,
+    pub title: String,
+    /// Website the feed belongs to
+    pub link: String,
+    /// Where the feed itself is published, used as the Atom id and self link
+    pub feed_url: Option<String>,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self {
+            format: FeedFormat::default(),
+            selection: FeedSelection::default(),
+            title: "Astronomy Picture of the Day".to_string(),
+            link: format!("{}/", APOD_BASE_URL),
+            feed_url: None,
+        }
+    }
+}
+
+/// Writes the selected entries newest first, with the full explanation as HTML content.
+/// Media is only enclosed in RSS feeds if it's cached, RSS requires its length.
+pub fn write_feed(
+    archive: &Archive<ApodEntry>,
+    media: Option<&dyn MediaCache>,
+    options: &FeedOptions,
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    let mut entries: Vec<&ApodEntry> = archive.iter().map(|(_, entry)| entry).collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+
+    let entries: Vec<&ApodEntry> = match options.selection {
+        FeedSelection::Latest(count) => entries.into_iter().take(count).collect(),
+        FeedSelection::Range(range) => entries
+            .into_iter()
+            .filter(|entry| range.contains(entry.date))
+            .collect(),
+    };
+
+    let document = match options.format {
+        FeedFormat::Rss => render_rss(&entries, media, options),
+        FeedFormat::Atom => render_atom(&entries, media, options),
+    };
+    writer.write_all(document.as_bytes())?;
+    Ok(())
+}
+
+pub fn write_feed_to_file(
+    archive: &Archive<ApodEntry>,
+    media: Option<&dyn MediaCache>,
+    options: &FeedOptions,
+    path: &Path,
+) -> Result<(), ExportError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_feed(archive, media, options, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn entry_link(entry: &ApodEntry) -> String {
+    entry
+        .link()
+        .unwrap_or_else(|| format!("{}/", APOD_BASE_URL))
+}
+
+fn render_content(entry: &ApodEntry) -> String {
+    let mut content = String::new();
+
+    match (entry.media.kind(), entry.media.url.as_deref()) {
+        (Some(kind), Some(url)) if kind.is_image() => {
+            let href = entry.media.highest_quality().unwrap_or(url);
+            content.push_str(&format!(
+                "<p><a href=\"{}\"><img src=\"{}\" alt=\"{}\"/></a></p>",
+                escape_xml(href),
+                escape_xml(url),
+                escape_xml(&entry.title)
+            ));
+        }
+        (_, Some(url)) => content.push_str(&format!(
+            "<p><a href=\"{}\">View media</a></p>",
+            escape_xml(url)
+        )),
+        _ => {}
+    }
+
+    content.push_str(&format!("<p>{}</p>", escape_xml(&entry.explanation)));
+    content
+}
+
+/// Media files as (url, MIME type, length in bytes), embedded videos have no file to enclose.
+/// The length is only known if the media is cached in its highest quality.
+fn enclosure<'a>(
+    entry: &'a ApodEntry,
+    media: Option<&dyn MediaCache>,
+) -> Option<(&'a str, &'static str, Option<usize>)> {
+    let mime_type = entry.media.kind()?.mime_type()?;
+    let length = media
+        .and_then(|media| media.peek_variant(entry.date, MediaVariant::Hd).ok()?)
+        .map(|cached| cached.data.len());
+    Some((entry.media.highest_quality()?, mime_type, length))
+}
+
+fn render_rss(
+    entries: &[&ApodEntry],
+    media: Option<&dyn MediaCache>,
+    options: &FeedOptions,
+) -> String {
+    let mut items = String::new();
+    for entry in entries {
+        let link = entry_link(entry);
+        items.push_str("    <item>\n");
+        items.push_str(&format!(
+            "      <title>{}</title>\n",
+            escape_xml(&entry.title)
+        ));
+        items.push_str(&format!("      <link>{}</link>\n", escape_xml(&link)));
+        items.push_str(&format!(
+            "      <guid isPermaLink=\"true\">{}</guid>\n",
+            escape_xml(&link)
+        ));
+        items.push_str(&format!(
+            "      <pubDate>{}</pubDate>\n",
+            entry.date.to_rfc2822()
+        ));
+        items.push_str(&format!(
+            "      <description>{}</description>\n",
+            escape_xml(&render_content(entry))
+        ));
+        // The length is required, readers may reject enclosures without the real one
+        if let Some((url, mime_type, Some(length))) = enclosure(entry, media) {
+            items.push_str(&format!(
+                "      <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>\n",
+                escape_xml(url),
+                length,
+                mime_type
+            ));
+        }
+        items.push_str("    </item>\n");
+    }
+
+    let self_link = options
+        .feed_url
+        .as_ref()
+        .map(|url| {
+            format!(
+                "    <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+                escape_xml(url)
+            )
+        })
+        .unwrap_or_default();
+    let last_build = entries
+        .first()
+        .map(|entry| {
+            format!(
+                "    <lastBuildDate>{}</lastBuildDate>\n",
+                entry.date.to_rfc2822()
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>{title}</title>
+    <link>{link}</link>
+    <description>{title}</description>
+    <language>en</language>
+{self_link}{last_build}{items}  </channel>
+</rss>
+"#,
+        title = escape_xml(&options.title),
+        link = escape_xml(&options.link),
+        self_link = self_link,
+        last_build = last_build,
+        items = items,
+    )
+}
+
+fn render_atom(
+    entries: &[&ApodEntry],
+    media: Option<&dyn MediaCache>,
+    options: &FeedOptions,
+) -> String {
+    let mut items = String::new();
+    for entry in entries {
+        let link = entry_link(entry);
+        items.push_str("  <entry>\n");
+        items.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_xml(&entry.title)
+        ));
+        items.push_str(&format!("    <id>{}</id>\n", escape_xml(&link)));
+        items.push_str(&format!(
+            "    <link rel=\"alternate\" href=\"{}\"/>\n",
+            escape_xml(&link)
+        ));
+        items.push_str(&format!(
+            "    <published>{}</published>\n",
+            entry.date.to_rfc3339()
+        ));
+        items.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            entry.date.to_rfc3339()
+        ));
+        // Unlike in RSS the length is optional
+        if let Some((url, mime_type, length)) = enclosure(entry, media) {
+            let length = length
+                .map(|length| format!(" length=\"{length}\""))
+                .unwrap_or_default();
+            items.push_str(&format!(
+                "    <link rel=\"enclosure\" type=\"{}\" href=\"{}\"{}/>\n",
+                mime_type,
+                escape_xml(url),
+                length
+            ));
+        }
+        items.push_str(&format!(
+            "    <content type=\"html\">{}</content>\n",
+            escape_xml(&render_content(entry))
+        ));
+        items.push_str("  </entry>\n");
+    }
+
+    let id = options.feed_url.as_ref().unwrap_or(&options.link);
+    let self_link = options
+        .feed_url
+        .as_ref()
+        .map(|url| format!("  <link rel=\"self\" href=\"{}\"/>\n", escape_xml(url)))
+        .unwrap_or_default();
+    // Atom requires an updated date even for empty feeds
+    let updated = entries
+        .first()
+        .map(|entry| entry.date)
+        .unwrap_or(ApodDate::START)
+        .to_rfc3339();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{title}</title>
+  <id>{id}</id>
+  <link rel="alternate" href="{link}"/>
+{self_link}  <updated>{updated}</updated>
+  <author><name>NASA Astronomy Picture of the Day</name></author>
+{items}</feed>
+"#,
+        title = escape_xml(&options.title),
+        id = escape_xml(id),
+        link = escape_xml(&options.link),
+        self_link = self_link,
+        updated = updated,
+        items = items,
+    )
+}
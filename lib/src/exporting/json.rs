@@ -0,0 +1,23 @@
+use crate::exporting::record::EntryRecord;
+use crate::exporting::ExportError;
+use std::io::Write;
+
+pub fn write_json(
+    records: impl Iterator<Item = EntryRecord>,
+    writer: impl Write,
+) -> Result<(), ExportError> {
+    let records: Vec<EntryRecord> = records.collect();
+    serde_json::to_writer_pretty(writer, &records)?;
+    Ok(())
+}
+
+pub fn write_ndjson(
+    records: impl Iterator<Item = EntryRecord>,
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    for record in records {
+        serde_json::to_writer(&mut writer, &record)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use crate::archiving::Archive;
+use crate::date::ApodDate;
+use crate::exporting::ExportError;
+use crate::media::{MediaCache, MediaType};
+use crate::parsing::extras::{EntryExtras, EntryLink};
+use crate::parsing::quality_control::quality_control;
+use crate::ApodEntry;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MarkdownReport {
+    pub notes: usize,
+    pub images: usize,
+    /// Entries whose note embeds the original media instead of a local image
+    pub missing_media: Vec<ApodDate>,
+}
+
+/// Writes one note per entry into `out_dir/YYYY/YYYY-MM-DD.md`, with cached images beside them.
+///
+/// Links of the explanation are taken from the extras, links to other entries of the archive
+/// become wiki links like `[[2005-03-04|text]]`, all others regular Markdown links.
+pub fn export_markdown_vault(
+    archive: &Archive<ApodEntry>,
+    extras: &HashMap<ApodDate, EntryExtras>,
+    media: Option<&dyn MediaCache>,
+    out_dir: &Path,
+) -> Result<MarkdownReport, ExportError> {
+    let mut report = MarkdownReport::default();
+
+    for (date, entry) in archive.iter() {
+        let dir = out_dir.join(date.year().to_string());
+        fs::create_dir_all(&dir)?;
+
+        let image = match media.map(|cache| cache.get(*date)) {
+            Some(Ok(Some(media_entry))) => match MediaType::sniff(&media_entry.data) {
+                Some(media_type) => {
+                    let file_name = format!("{}.{}", date, media_type.extension());
+                    fs::write(dir.join(&file_name), &media_entry.data)?;
+                    report.images += 1;
+                    Some(file_name)
+                }
+                None => None,
+            },
+            _ => None,
+        };
+        if image.is_none() {
+            report.missing_media.push(*date);
+        }
+
+        let note = render_note(entry, extras.get(date), image.as_deref(), archive);
+        fs::write(dir.join(format!("{}.md", date)), note)?;
+        report.notes += 1;
+    }
+
+    Ok(report)
+}
+
+fn render_note(
+    entry: &ApodEntry,
+    extras: Option<&EntryExtras>,
+    image: Option<&str>,
+    archive: &Archive<ApodEntry>,
+) -> String {
+    let mut warnings: Vec<String> = quality_control(entry)
+        .into_iter()
+        .map(|warning| warning.to_string())
+        .collect();
+    warnings.sort();
+
+    let mut note = String::from("---\n");
+    note.push_str(&format!("date: {}\n", entry.date));
+    note.push_str(&format!("title: {}\n", yaml_string(&entry.title)));
+    if let Some(link) = entry.link() {
+        note.push_str(&format!("apod_url: {}\n", yaml_string(&link)));
+    }
+    if let Some(url) = &entry.media.url {
+        note.push_str(&format!("media_url: {}\n", yaml_string(url)));
+    }
+    if let Some(hd_url) = &entry.media.hd_url {
+        note.push_str(&format!("hd_url: {}\n", yaml_string(hd_url)));
+    }
+    if let Some(kind) = entry.media.kind() {
+        note.push_str(&format!("media_kind: {}\n", kind));
+    }
+    if let Some(credits) = extras.and_then(|extras| extras.credits.as_ref()) {
+        note.push_str(&format!("credits: {}\n", yaml_string(credits)));
+    }
+    note.push_str(&format!("warnings: [{}]\n", warnings.join(", ")));
+    note.push_str("---\n\n");
+
+    let title = escape_markdown(&entry.title);
+    note.push_str(&format!("# {}\n\n", title));
+
+    let is_image = entry.media.kind().is_some_and(|kind| kind.is_image());
+    match (image, entry.media.url.as_deref()) {
+        (Some(file_name), _) => note.push_str(&format!("![{}]({})\n\n", title, file_name)),
+        (None, Some(url)) if is_image => {
+            note.push_str(&format!("![{}]({})\n\n", title, escape_url(url)))
+        }
+        (None, Some(url)) => note.push_str(&format!("[Media]({})\n\n", escape_url(url))),
+        (None, None) => {}
+    }
+
+    let links = extras
+        .map(|extras| extras.links.as_slice())
+        .unwrap_or_default();
+    note.push_str(&link_explanation(&entry.explanation, links, archive));
+    note.push('\n');
+
+    note
+}
+
+/// Replaces the link texts in order of appearance, the explanation is plain text and escaped
+fn link_explanation(
+    explanation: &str,
+    links: &[EntryLink],
+    archive: &Archive<ApodEntry>,
+) -> String {
+    let mut linked = String::with_capacity(explanation.len());
+    let mut rest = explanation;
+
+    for link in links {
+        if link.text.is_empty() {
+            continue;
+        }
+        let Some(pos) = rest.find(&link.text) else {
+            continue;
+        };
+
+        linked.push_str(&escape_markdown(&rest[..pos]));
+        let text = escape_markdown(&link.text);
+        match link.apod_date().filter(|date| archive.has_date(*date)) {
+            Some(date) => linked.push_str(&format!("[[{}|{}]]", date, text)),
+            None => linked.push_str(&format!("[{}]({})", text, escape_url(&link.url))),
+        }
+        rest = &rest[pos + link.text.len()..];
+    }
+
+    linked.push_str(&escape_markdown(rest));
+    linked
+}
+
+/// JSON strings are valid double quoted YAML scalars
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// Backslash escapes characters with a meaning inside a line of Markdown
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '<' | '>' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_url(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
@@ -0,0 +1,64 @@
+use crate::exporting::ExportColumn;
+use crate::parsing::quality_control::quality_control;
+use crate::ApodEntry;
+
+/// Flat representation of an entry for exchange formats, with ISO formatted dates.
+/// Fields of columns that were not selected are left out.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EntryRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hd_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<String>>,
+}
+
+impl EntryRecord {
+    pub fn from_entry(entry: &ApodEntry, columns: &[ExportColumn]) -> Self {
+        let mut record = Self::default();
+        for column in columns {
+            match column {
+                ExportColumn::Date => record.date = Some(entry.date.to_string()),
+                ExportColumn::Title => record.title = Some(entry.title.clone()),
+                ExportColumn::Explanation => record.explanation = Some(entry.explanation.clone()),
+                ExportColumn::MediaUrl => record.media_url = entry.media.url.clone(),
+                ExportColumn::HdUrl => record.hd_url = entry.media.hd_url.clone(),
+                ExportColumn::MediaKind => {
+                    record.media_kind = entry.media.kind().map(|kind| kind.to_string())
+                }
+                ExportColumn::Warnings => {
+                    let mut warnings: Vec<String> = quality_control(entry)
+                        .into_iter()
+                        .map(|warning| warning.to_string())
+                        .collect();
+                    warnings.sort();
+                    record.warnings = Some(warnings);
+                }
+            }
+        }
+        record
+    }
+
+    /// Single text value of a column, multiple warnings are separated by `;`
+    pub fn field(&self, column: ExportColumn) -> String {
+        match column {
+            ExportColumn::Date => self.date.clone(),
+            ExportColumn::Title => self.title.clone(),
+            ExportColumn::Explanation => self.explanation.clone(),
+            ExportColumn::MediaUrl => self.media_url.clone(),
+            ExportColumn::HdUrl => self.hd_url.clone(),
+            ExportColumn::MediaKind => self.media_kind.clone(),
+            ExportColumn::Warnings => self.warnings.as_ref().map(|warnings| warnings.join(";")),
+        }
+        .unwrap_or_default()
+    }
+}
@@ -0,0 +1,486 @@
+use crate::archiving::Archive;
+use crate::date::ApodDate;
+use crate::exporting::markup::escape_xml;
+use crate::exporting::ExportError;
+use crate::media::{parse_media_file_name, MediaVariant};
+use crate::ApodEntry;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STYLE: &str = r#"body { font-family: sans-serif; margin: 0 auto; max-width: 64rem; padding: 1rem; background: #111; color: #ddd; }
+a { color: #8ab4f8; }
+header, nav.pager { display: flex; gap: 1rem; justify-content: space-between; flex-wrap: wrap; margin: 1rem 0; }
+.media img { max-width: 100%; }
+.explanation { line-height: 1.5; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(12rem, 1fr)); gap: 1rem; }
+.card { display: block; text-decoration: none; }
+.card img { width: 100%; aspect-ratio: 1; object-fit: cover; background: #222; }
+.card .placeholder { display: flex; align-items: center; justify-content: center; aspect-ratio: 1; background: #222; color: #888; }
+.date { color: #888; font-size: 0.9em; }
+input[type=search] { width: 100%; padding: 0.5rem; font-size: 1rem; }
+"#;
+
+/// The index is a script instead of JSON, browsers refuse to fetch files from file:// pages
+const SEARCH_SCRIPT: &str = r#"<script src="search.js"></script>
+<script>
+const input = document.getElementById("query");
+const results = document.getElementById("results");
+{
+    const entries = window.searchEntries || [];
+    const render = () => {
+        const query = input.value.trim().toLowerCase();
+        results.replaceChildren();
+        if (query.length < 2) return;
+        for (const entry of entries.filter((e) => e.title.toLowerCase().includes(query)).slice(0, 200)) {
+            const item = document.createElement("li");
+            const link = document.createElement("a");
+            link.href = entry.url;
+            link.textContent = entry.title;
+            item.append(entry.date + " ", link);
+            results.append(item);
+        }
+    };
+    input.addEventListener("input", render);
+    render();
+}
+</script>"#;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SiteOptions {
+    /// Shown in the page titles and header
+    pub title: String,
+    /// Maximum width and height of thumbnails in pixels
+    pub thumbnail_size: u32,
+}
+
+impl Default for SiteOptions {
+    fn default() -> Self {
+        Self {
+            title: "Astronomy Picture of the Day".to_string(),
+            thumbnail_size: 256,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SiteReport {
+    pub pages: usize,
+    pub media_files: usize,
+    pub thumbnails: usize,
+    /// Image entries without a file in the media directory, their pages link the original media
+    pub missing_media: Vec<ApodDate>,
+    /// Media files that could not be decoded into a thumbnail
+    pub failed_thumbnails: Vec<ApodDate>,
+}
+
+#[derive(serde::Serialize)]
+struct SearchRecord<'a> {
+    date: String,
+    title: &'a str,
+    url: String,
+    thumbnail: Option<String>,
+}
+
+/// Local files of a single entry, as paths relative to the site root
+#[derive(Default)]
+struct SiteMedia {
+    media: Option<String>,
+    thumbnail: Option<String>,
+}
+
+/// Renders a browsable static site into `out_dir` that works without any server code.
+///
+/// Media files are looked up in `media_dir` by their date, named either `apYYMMDD.*` like the
+/// APOD pages or `YYYY-MM-DD.*`, or like the files of a
+/// [`FsMediaCache`](crate::media::fs::FsMediaCache) which can be used as the media directory as is.
+/// They are copied next to the pages together with thumbnails.
+/// Files that already exist in `out_dir` are kept, so repeated exports only add what changed.
+pub fn export_site(
+    archive: &Archive<ApodEntry>,
+    media_dir: Option<&Path>,
+    out_dir: &Path,
+    options: &SiteOptions,
+) -> Result<SiteReport, ExportError> {
+    let mut entries: Vec<&ApodEntry> = archive.iter().map(|(_, entry)| entry).collect();
+    entries.sort_by_key(|entry| entry.date);
+
+    let media_files = match media_dir {
+        Some(dir) => find_media_files(dir)?,
+        None => HashMap::new(),
+    };
+
+    fs::create_dir_all(out_dir)?;
+    fs::write(out_dir.join("style.css"), STYLE)?;
+
+    let mut report = SiteReport::default();
+    let mut site_media = HashMap::new();
+    for entry in entries.iter() {
+        let media = match media_files.get(&entry.date) {
+            Some(path) => copy_media(entry.date, path, out_dir, options, &mut report)?,
+            None => {
+                if entry.media.kind().is_some_and(|kind| kind.is_image()) {
+                    report.missing_media.push(entry.date);
+                }
+                SiteMedia::default()
+            }
+        };
+        site_media.insert(entry.date, media);
+    }
+
+    for (i, entry) in entries.iter().enumerate() {
+        let prev = i.checked_sub(1).and_then(|i| entries.get(i)).copied();
+        let next = entries.get(i + 1).copied();
+        let html = render_day(entry, &site_media[&entry.date], prev, next, options);
+
+        let path = out_dir.join(day_path(entry.date));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, html)?;
+        report.pages += 1;
+    }
+
+    let mut years: BTreeMap<i32, BTreeMap<u32, Vec<&ApodEntry>>> = BTreeMap::new();
+    for entry in entries.iter() {
+        years
+            .entry(entry.date.year())
+            .or_default()
+            .entry(month(entry.date))
+            .or_default()
+            .push(entry);
+    }
+
+    for (year, months) in years.iter() {
+        for (month, month_entries) in months.iter() {
+            let html = render_month(*year, *month, month_entries, &site_media, options);
+            let path = out_dir.join(format!("{}/{:02}/index.html", year, month));
+            fs::write(path, html)?;
+            report.pages += 1;
+        }
+
+        let html = render_year(*year, months, options);
+        fs::write(out_dir.join(format!("{}/index.html", year)), html)?;
+        report.pages += 1;
+    }
+
+    fs::write(
+        out_dir.join("index.html"),
+        render_index(&years, entries.last().copied(), options),
+    )?;
+    fs::write(out_dir.join("search.html"), render_search(options))?;
+    report.pages += 2;
+
+    let search_records: Vec<SearchRecord> = entries
+        .iter()
+        .map(|entry| SearchRecord {
+            date: entry.date.to_string(),
+            title: &entry.title,
+            url: day_path(entry.date),
+            thumbnail: site_media[&entry.date].thumbnail.clone(),
+        })
+        .collect();
+    fs::write(
+        out_dir.join("search.js"),
+        format!(
+            "window.searchEntries = {};\n",
+            serde_json::to_string(&search_records)?
+        ),
+    )?;
+
+    Ok(report)
+}
+
+/// The best full size file per date, thumbnails and posters are skipped
+fn find_media_files(dir: &Path) -> Result<HashMap<ApodDate, PathBuf>, ExportError> {
+    let rank = |variant: &MediaVariant| MediaVariant::FULL_SIZE.iter().position(|v| v == variant);
+    let mut files: HashMap<ApodDate, (MediaVariant, PathBuf)> = HashMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for dir_entry in fs::read_dir(&dir)? {
+            let path = dir_entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+
+            let Some((date, variant)) = media_file_date(&path) else {
+                continue;
+            };
+            if !variant.is_full_size() {
+                continue;
+            }
+            match files.get(&date) {
+                Some((existing, _)) if rank(existing) <= rank(&variant) => {}
+                _ => {
+                    files.insert(date, (variant, path));
+                }
+            }
+        }
+    }
+    Ok(files
+        .into_iter()
+        .map(|(date, (_, path))| (date, path))
+        .collect())
+}
+
+/// Files without a variant suffix are assumed to be the highest quality available
+fn media_file_date(path: &Path) -> Option<(ApodDate, MediaVariant)> {
+    let name = path.file_name()?.to_str()?;
+    if let Some(parsed) = parse_media_file_name(name) {
+        return Some(parsed);
+    }
+
+    let stem = path.file_stem()?.to_str()?;
+    let date = stem
+        .strip_prefix("ap")
+        .and_then(ApodDate::from_page_code)
+        .or_else(|| ApodDate::from_iso(stem))?;
+    Some((date, MediaVariant::Hd))
+}
+
+fn copy_media(
+    date: ApodDate,
+    source: &Path,
+    out_dir: &Path,
+    options: &SiteOptions,
+    report: &mut SiteReport,
+) -> Result<SiteMedia, ExportError> {
+    let Some(file_name) = source.file_name().and_then(|name| name.to_str()) else {
+        return Ok(SiteMedia::default());
+    };
+
+    let media = format!("media/{}/{}", date.year(), file_name);
+    let media_path = out_dir.join(&media);
+    if !media_path.exists() {
+        if let Some(parent) = media_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source, &media_path)?;
+    }
+    report.media_files += 1;
+
+    let thumbnail = format!("thumbs/{}/{}.jpg", date.year(), date.page_code());
+    let thumbnail_path = out_dir.join(&thumbnail);
+    if !thumbnail_path.exists() {
+        if let Some(parent) = thumbnail_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let size = options.thumbnail_size;
+        let saved = image::open(source)
+            .and_then(|image| image.thumbnail(size, size).to_rgb8().save(&thumbnail_path));
+        if saved.is_err() {
+            report.failed_thumbnails.push(date);
+            return Ok(SiteMedia {
+                media: Some(media),
+                thumbnail: None,
+            });
+        }
+    }
+    report.thumbnails += 1;
+
+    Ok(SiteMedia {
+        media: Some(media),
+        thumbnail: Some(thumbnail),
+    })
+}
+
+fn month(date: ApodDate) -> u32 {
+    date.format("%m").parse().unwrap_or(1)
+}
+
+fn month_name(month: u32) -> String {
+    ApodDate::from_ymd(2000, month, 1)
+        .map(|date| date.format("%B"))
+        .unwrap_or_default()
+}
+
+/// Path of a day page relative to the site root
+fn day_path(date: ApodDate) -> String {
+    date.format("%Y/%m/%d.html")
+}
+
+fn render_page(options: &SiteOptions, title: &str, root: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} - {site}</title>
+<link rel="stylesheet" href="{root}style.css">
+</head>
+<body>
+<header><a href="{root}index.html">{site}</a><a href="{root}search.html">Search</a></header>
+{body}
+</body>
+</html>
+"#,
+        title = escape_xml(title),
+        site = escape_xml(&options.title),
+        root = root,
+        body = body,
+    )
+}
+
+fn render_day(
+    entry: &ApodEntry,
+    media: &SiteMedia,
+    prev: Option<&ApodEntry>,
+    next: Option<&ApodEntry>,
+    options: &SiteOptions,
+) -> String {
+    let root = "../../";
+    let mut body = String::new();
+
+    body.push_str(&format!(
+        "<nav><a href=\"../../{year}/index.html\">{year}</a> / <a href=\"index.html\">{month}</a></nav>\n",
+        year = entry.date.year(),
+        month = month_name(month(entry.date)),
+    ));
+    body.push_str(&format!(
+        "<h1>{}</h1>\n<p class=\"date\">{}</p>\n",
+        escape_xml(&entry.title),
+        entry.date.format("%B %-d, %Y")
+    ));
+
+    body.push_str("<div class=\"media\">");
+    if let Some(media_path) = &media.media {
+        body.push_str(&format!(
+            "<a href=\"{root}{path}\"><img src=\"{root}{path}\" alt=\"{alt}\"></a>",
+            root = root,
+            path = escape_xml(media_path),
+            alt = escape_xml(&entry.title)
+        ));
+    } else if let Some(url) = entry.media.highest_quality() {
+        body.push_str(&format!(
+            "<p><a href=\"{}\">View original media</a></p>",
+            escape_xml(url)
+        ));
+    }
+    body.push_str("</div>\n");
+
+    body.push_str(&format!(
+        "<p class=\"explanation\">{}</p>\n",
+        escape_xml(&entry.explanation)
+    ));
+
+    body.push_str("<nav class=\"pager\">");
+    match prev {
+        Some(prev) => body.push_str(&format!(
+            "<a href=\"{}{}\">&larr; {}</a>",
+            root,
+            day_path(prev.date),
+            prev.date
+        )),
+        None => body.push_str("<span></span>"),
+    }
+    if let Some(link) = entry.link() {
+        body.push_str(&format!("<a href=\"{}\">Original page</a>", link));
+    }
+    match next {
+        Some(next) => body.push_str(&format!(
+            "<a href=\"{}{}\">{} &rarr;</a>",
+            root,
+            day_path(next.date),
+            next.date
+        )),
+        None => body.push_str("<span></span>"),
+    }
+    body.push_str("</nav>\n");
+
+    render_page(options, &entry.title, root, &body)
+}
+
+fn render_month(
+    year: i32,
+    month: u32,
+    entries: &[&ApodEntry],
+    site_media: &HashMap<ApodDate, SiteMedia>,
+    options: &SiteOptions,
+) -> String {
+    let root = "../../";
+    let title = format!("{} {}", month_name(month), year);
+    let mut body = format!(
+        "<nav><a href=\"../index.html\">{}</a></nav>\n<h1>{}</h1>\n<div class=\"grid\">\n",
+        year, title
+    );
+
+    for entry in entries {
+        let preview = match &site_media[&entry.date].thumbnail {
+            Some(thumbnail) => format!(
+                "<img src=\"{}{}\" alt=\"\" loading=\"lazy\">",
+                root,
+                escape_xml(thumbnail)
+            ),
+            None => "<div class=\"placeholder\">No preview</div>".to_string(),
+        };
+        body.push_str(&format!(
+            "<a class=\"card\" href=\"{day}\">{preview}<div>{title}</div><div class=\"date\">{date}</div></a>\n",
+            day = entry.date.format("%d.html"),
+            preview = preview,
+            title = escape_xml(&entry.title),
+            date = entry.date,
+        ));
+    }
+    body.push_str("</div>\n");
+
+    render_page(options, &title, root, &body)
+}
+
+fn render_year(
+    year: i32,
+    months: &BTreeMap<u32, Vec<&ApodEntry>>,
+    options: &SiteOptions,
+) -> String {
+    let mut body = format!("<h1>{}</h1>\n<ul>\n", year);
+    for (month, entries) in months.iter() {
+        body.push_str(&format!(
+            "<li><a href=\"{:02}/index.html\">{}</a> ({} entries)</li>\n",
+            month,
+            month_name(*month),
+            entries.len()
+        ));
+    }
+    body.push_str("</ul>\n");
+
+    render_page(options, &year.to_string(), "../", &body)
+}
+
+fn render_index(
+    years: &BTreeMap<i32, BTreeMap<u32, Vec<&ApodEntry>>>,
+    latest: Option<&ApodEntry>,
+    options: &SiteOptions,
+) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape_xml(&options.title));
+    if let Some(latest) = latest {
+        body.push_str(&format!(
+            "<p>Latest: <a href=\"{}\">{}</a> ({})</p>\n",
+            day_path(latest.date),
+            escape_xml(&latest.title),
+            latest.date
+        ));
+    }
+
+    body.push_str("<ul>\n");
+    for (year, months) in years.iter().rev() {
+        let count: usize = months.values().map(Vec::len).sum();
+        body.push_str(&format!(
+            "<li><a href=\"{year}/index.html\">{year}</a> ({count} entries)</li>\n",
+            year = year,
+            count = count
+        ));
+    }
+    body.push_str("</ul>\n");
+
+    render_page(options, "Archive", "", &body)
+}
+
+fn render_search(options: &SiteOptions) -> String {
+    let body = format!(
+        "<h1>Search</h1>\n<input id=\"query\" type=\"search\" placeholder=\"Search titles\" autofocus>\n<ul id=\"results\"></ul>\n{}\n",
+        SEARCH_SCRIPT
+    );
+    render_page(options, "Search", "", &body)
+}
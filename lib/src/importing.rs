@@ -0,0 +1,256 @@
+use crate::archiving::Archive;
+use crate::date::ApodDate;
+use crate::exporting::record::EntryRecord;
+use crate::exporting::ExportColumn;
+use crate::parsing::media_url::MediaUrl;
+use crate::ApodEntry;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A problem with a single row, rows with errors are skipped while the rest of the file is imported
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RowError {
+    #[error("Date '{0}' was already imported from a previous row")]
+    DuplicateDate(ApodDate),
+    #[error("Invalid date '{0}', expected YYYY-MM-DD")]
+    InvalidDate(String),
+    #[error("Date {0} is before the first APOD")]
+    DateOutOfRange(ApodDate),
+    #[error("Malformed row: {0}")]
+    Malformed(String),
+    #[error("Missing field '{0}'")]
+    MissingField(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowIssue {
+    /// 1-based line for CSV and NDJSON, 1-based array position for JSON
+    pub row: usize,
+    pub error: RowError,
+}
+
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum ImportFormat {
+    /// A single JSON array of entry objects
+    #[default]
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    Csv,
+}
+
+impl ImportFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub archive: Archive<ApodEntry>,
+    pub issues: Vec<RowIssue>,
+    /// Number of rows read, including the ones that failed validation
+    pub rows: usize,
+}
+
+impl ImportReport {
+    fn add_record(&mut self, row: usize, record: EntryRecord) {
+        self.rows += 1;
+
+        let errors = match record_to_entry(record) {
+            Ok(entry) if self.archive.has_date(entry.date) => {
+                vec![RowError::DuplicateDate(entry.date)]
+            }
+            Ok(entry) => {
+                self.archive.push(entry);
+                return;
+            }
+            Err(errors) => errors,
+        };
+
+        self.issues
+            .extend(errors.into_iter().map(|error| RowIssue { row, error }));
+    }
+
+    fn add_malformed(&mut self, row: usize, message: impl Into<String>) {
+        self.rows += 1;
+        self.issues.push(RowIssue {
+            row,
+            error: RowError::Malformed(message.into()),
+        });
+    }
+
+    pub fn failed_rows(&self) -> usize {
+        self.issues
+            .iter()
+            .map(|issue| issue.row)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+/// Reads entries in the same shape the exporters write them.
+/// Only a broken file structure fails the whole import, invalid rows end up in the report.
+pub fn import_entries(
+    format: ImportFormat,
+    reader: impl Read,
+) -> Result<ImportReport, ImportError> {
+    match format {
+        ImportFormat::Json => import_json(reader),
+        ImportFormat::Ndjson => import_ndjson(reader),
+        ImportFormat::Csv => import_csv(reader),
+    }
+}
+
+pub fn import_entries_from_file(
+    format: ImportFormat,
+    path: &Path,
+) -> Result<ImportReport, ImportError> {
+    import_entries(format, BufReader::new(File::open(path)?))
+}
+
+fn import_json(reader: impl Read) -> Result<ImportReport, ImportError> {
+    let values: Vec<serde_json::Value> = serde_json::from_reader(reader)?;
+
+    let mut report = ImportReport::default();
+    for (i, value) in values.into_iter().enumerate() {
+        match serde_json::from_value::<EntryRecord>(value) {
+            Ok(record) => report.add_record(i + 1, record),
+            Err(err) => report.add_malformed(i + 1, err.to_string()),
+        }
+    }
+
+    Ok(report)
+}
+
+fn import_ndjson(reader: impl Read) -> Result<ImportReport, ImportError> {
+    let mut report = ImportReport::default();
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<EntryRecord>(&line) {
+            Ok(record) => report.add_record(i + 1, record),
+            Err(err) => report.add_malformed(i + 1, err.to_string()),
+        }
+    }
+
+    Ok(report)
+}
+
+fn import_csv(reader: impl Read) -> Result<ImportReport, ImportError> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let columns: Vec<Option<ExportColumn>> = csv_reader
+        .headers()?
+        .iter()
+        .map(|header| {
+            ExportColumn::ALL
+                .into_iter()
+                .find(|column| column.name() == header.trim())
+        })
+        .collect();
+
+    let mut report = ImportReport::default();
+    for (i, result) in csv_reader.records().enumerate() {
+        let row = match &result {
+            Ok(record) => record.position().map(|pos| pos.line() as usize),
+            Err(err) => err.position().map(|pos| pos.line() as usize),
+        }
+        .unwrap_or(i + 2);
+
+        let record = match result {
+            Ok(record) => record,
+            Err(err) => {
+                report.add_malformed(row, err.to_string());
+                continue;
+            }
+        };
+
+        let mut entry_record = EntryRecord::default();
+        for (column, value) in columns.iter().zip(record.iter()) {
+            let value = (!value.is_empty()).then(|| value.to_string());
+            match column {
+                Some(ExportColumn::Date) => entry_record.date = value,
+                Some(ExportColumn::Title) => entry_record.title = value,
+                Some(ExportColumn::Explanation) => entry_record.explanation = value,
+                Some(ExportColumn::MediaUrl) => entry_record.media_url = value,
+                Some(ExportColumn::HdUrl) => entry_record.hd_url = value,
+                // Derived from the entry itself, not imported
+                Some(ExportColumn::MediaKind) | Some(ExportColumn::Warnings) | None => {}
+            }
+        }
+
+        report.add_record(row, entry_record);
+    }
+
+    Ok(report)
+}
+
+fn record_to_entry(record: EntryRecord) -> Result<ApodEntry, Vec<RowError>> {
+    let mut errors = Vec::new();
+
+    let date = match record.date.as_deref().map(str::trim) {
+        None | Some("") => {
+            errors.push(RowError::MissingField(ExportColumn::Date.name()));
+            None
+        }
+        Some(date_str) => match ApodDate::from_iso(date_str) {
+            Some(date) if date < ApodDate::START => {
+                errors.push(RowError::DateOutOfRange(date));
+                None
+            }
+            Some(date) => Some(date),
+            None => {
+                errors.push(RowError::InvalidDate(date_str.to_string()));
+                None
+            }
+        },
+    };
+
+    // Blank text counts as missing, like an empty CSV cell
+    let title = record.title.filter(|title| !title.trim().is_empty());
+    if title.is_none() {
+        errors.push(RowError::MissingField(ExportColumn::Title.name()));
+    }
+
+    let explanation = record
+        .explanation
+        .filter(|explanation| !explanation.trim().is_empty());
+    if explanation.is_none() {
+        errors.push(RowError::MissingField(ExportColumn::Explanation.name()));
+    }
+
+    match (date, title, explanation) {
+        (Some(date), Some(title), Some(explanation)) if errors.is_empty() => Ok(ApodEntry {
+            date,
+            title,
+            explanation,
+            media: MediaUrl {
+                url: record.media_url.filter(|url| !url.is_empty()),
+                hd_url: record.hd_url.filter(|url| !url.is_empty()),
+            },
+        }),
+        _ => Err(errors),
+    }
+}
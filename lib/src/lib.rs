@@ -2,8 +2,14 @@
 pub mod archiving;
 pub mod client;
 pub mod date;
+#[cfg(feature = "exporting")]
+pub mod exporting;
+#[cfg(feature = "importing")]
+pub mod importing;
 pub mod media;
 pub mod parsing;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 use crate::parsing::media_url::MediaUrl;
 pub use async_trait;
@@ -1,13 +1,183 @@
+use crate::date::range::ApodDateRange;
 use crate::date::ApodDate;
+use std::collections::BTreeMap;
+use std::fmt::Display;
 
+#[cfg(feature = "bulk-download")]
+pub mod download;
+#[cfg(feature = "fs-media-cache")]
+pub mod fs;
 #[cfg(feature = "heed-media-cache")]
 pub mod heed;
+#[cfg(feature = "media-index")]
+pub mod index;
+#[cfg(feature = "media-index")]
+pub mod metadata;
+#[cfg(feature = "media-index")]
+pub mod palette;
+#[cfg(feature = "thumbnails")]
+pub mod thumbnail;
+#[cfg(feature = "media-verification")]
+pub mod verify;
+pub mod video;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Brands of HEIF and AVIF images, which share the container format of MP4
+const IMAGE_BRANDS: [&[u8]; 6] = [b"avif", b"avis", b"heic", b"heix", b"mif1", b"msf1"];
+
+/// Media cached before the type was detected is always labeled as PNG,
+/// sniff the data when the actual format matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[repr(u8)]
 pub enum MediaType {
     ImagePNG = 0,
+    ImageJPEG = 1,
+    ImageGIF = 2,
+    ImageWEBP = 3,
+    VideoMP4 = 4,
+    /// Anything that is not a known image or video format
+    Other = 255,
+}
+
+impl MediaType {
+    /// Detects the image or video format from its magic bytes
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::ImagePNG)
+        } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::ImageJPEG)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(Self::ImageGIF)
+        } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+            Some(Self::ImageWEBP)
+        } else if data.len() >= 12
+            && &data[4..8] == b"ftyp"
+            && !IMAGE_BRANDS.contains(&&data[8..12])
+        {
+            Some(Self::VideoMP4)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MediaType::ImagePNG => "png",
+            MediaType::ImageJPEG => "jpg",
+            MediaType::ImageGIF => "gif",
+            MediaType::ImageWEBP => "webp",
+            MediaType::VideoMP4 => "mp4",
+            MediaType::Other => "bin",
+        }
+    }
+
+    /// Inverse of [`MediaType::extension`], also accepting common alternatives like `jpeg`
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => MediaType::ImagePNG,
+            "jpg" | "jpeg" => MediaType::ImageJPEG,
+            "gif" => MediaType::ImageGIF,
+            "webp" => MediaType::ImageWEBP,
+            "mp4" | "m4v" => MediaType::VideoMP4,
+            _ => MediaType::Other,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            MediaType::ImagePNG => "image/png",
+            MediaType::ImageJPEG => "image/jpeg",
+            MediaType::ImageGIF => "image/gif",
+            MediaType::ImageWEBP => "image/webp",
+            MediaType::VideoMP4 => "video/mp4",
+            MediaType::Other => "application/octet-stream",
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        !matches!(self, MediaType::Other | MediaType::VideoMP4)
+    }
+
+    pub fn is_video(&self) -> bool {
+        *self == MediaType::VideoMP4
+    }
+}
+
+/// The different files that can be cached for a single date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MediaVariant {
+    /// The media of the standard url, shown on the APOD page itself
+    Standard,
+    /// The media of the HD url, or of the standard url if there is no separate HD version
+    Hd,
+    /// Downscaled preview, the longest edge at most this many pixels
+    Thumbnail(u16),
+    /// Still image representing a video
+    Poster,
+}
+
+impl MediaVariant {
+    /// Full size variants, from best to worst quality
+    pub const FULL_SIZE: [Self; 2] = [Self::Hd, Self::Standard];
+
+    pub fn is_full_size(&self) -> bool {
+        Self::FULL_SIZE.contains(self)
+    }
+
+    /// Distinguishes the files of a date, see [`media_file_name`]
+    pub fn file_suffix(&self) -> String {
+        match self {
+            MediaVariant::Standard => "std".to_string(),
+            MediaVariant::Hd => "hd".to_string(),
+            MediaVariant::Thumbnail(size) => format!("thumb{size}"),
+            MediaVariant::Poster => "poster".to_string(),
+        }
+    }
+
+    pub fn from_file_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "std" => Some(MediaVariant::Standard),
+            "hd" => Some(MediaVariant::Hd),
+            "poster" => Some(MediaVariant::Poster),
+            _ => suffix
+                .strip_prefix("thumb")
+                .and_then(|size| size.parse().ok())
+                .map(MediaVariant::Thumbnail),
+        }
+    }
+}
+
+/// Like `ap050304-hd.jpg`, after the APOD page of the date
+pub fn media_file_name(date: ApodDate, variant: MediaVariant, media_type: MediaType) -> String {
+    format!(
+        "ap{}-{}.{}",
+        date.page_code(),
+        variant.file_suffix(),
+        media_type.extension()
+    )
+}
+
+/// Inverse of [`media_file_name`]
+pub fn parse_media_file_name(name: &str) -> Option<(ApodDate, MediaVariant)> {
+    let (stem, _) = name.rsplit_once('.')?;
+    let (code, suffix) = stem.strip_prefix("ap")?.split_once('-')?;
+    Some((
+        ApodDate::from_page_code(code)?,
+        MediaVariant::from_file_suffix(suffix)?,
+    ))
+}
+
+impl Display for MediaVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaVariant::Standard => write!(f, "Standard"),
+            MediaVariant::Hd => write!(f, "HD"),
+            MediaVariant::Thumbnail(size) => write!(f, "Thumbnail {size}px"),
+            MediaVariant::Poster => write!(f, "Poster"),
+        }
+    }
 }
 
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
@@ -16,15 +186,274 @@ pub struct MediaEntry {
     pub data: Vec<u8>,
 }
 
+/// SHA-256 and length of media data when it was stored, to detect corruption on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentHash {
+    pub sha256: [u8; 32],
+    pub size: u64,
+}
+
+impl ContentHash {
+    #[cfg(feature = "sha2")]
+    pub fn of(data: &[u8]) -> Self {
+        use sha2::Digest;
+        Self {
+            sha256: sha2::Sha256::digest(data).into(),
+            size: data.len() as u64,
+        }
+    }
+
+    /// Fixed size encoding, the checksum followed by the big endian size
+    pub fn to_bytes(&self) -> [u8; 40] {
+        let mut bytes = [0; 40];
+        bytes[..32].copy_from_slice(&self.sha256);
+        bytes[32..].copy_from_slice(&self.size.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (sha256, size) = bytes.split_first_chunk::<32>()?;
+        Some(Self {
+            sha256: *sha256,
+            size: u64::from_be_bytes(size.try_into().ok()?),
+        })
+    }
+
+    /// Compares the data with the stored hash, the cheap size check first
+    #[cfg(feature = "sha2")]
+    pub fn verify(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+        data: &[u8],
+    ) -> Result<(), IntegrityError> {
+        if data.len() as u64 != self.size {
+            return Err(IntegrityError::SizeMismatch {
+                date,
+                variant,
+                expected: self.size,
+                actual: data.len() as u64,
+            });
+        }
+        if Self::of(data).sha256 != self.sha256 {
+            return Err(IntegrityError::ChecksumMismatch { date, variant });
+        }
+        Ok(())
+    }
+}
+
+/// Returned by reads of media that changed since it was stored
+#[derive(Debug, thiserror::Error)]
+pub enum IntegrityError {
+    #[error("{variant} media of {date} has {actual} bytes instead of {expected}")]
+    SizeMismatch {
+        date: ApodDate,
+        variant: MediaVariant,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("{variant} media of {date} does not match its checksum")]
+    ChecksumMismatch {
+        date: ApodDate,
+        variant: MediaVariant,
+    },
+}
+
+/// A single cached file, without its data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedMedia {
+    pub date: ApodDate,
+    pub variant: MediaVariant,
+    pub media_type: MediaType,
+    /// Bytes occupied in the cache, including any encoding overhead
+    pub size: u64,
+    /// Unix timestamp in seconds of the last read, if the cache tracks it
+    pub last_access: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MediaCacheStats {
+    pub count: usize,
+    pub total_bytes: u64,
+    /// Count and bytes per variant
+    pub by_variant: BTreeMap<MediaVariant, (usize, u64)>,
+}
+
+impl MediaCacheStats {
+    pub fn from_media<'a>(media: impl IntoIterator<Item = &'a CachedMedia>) -> Self {
+        let mut stats = Self::default();
+        for cached in media {
+            stats.count += 1;
+            stats.total_bytes += cached.size;
+            let (count, bytes) = stats.by_variant.entry(cached.variant).or_default();
+            *count += 1;
+            *bytes += cached.size;
+        }
+        stats
+    }
+}
+
+/// Which media to remove from a cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Removes the least recently accessed media until the cache is at most this many bytes large.
+    /// Media that was never accessed goes first.
+    MaxBytes(u64),
+    /// Removes all media that was not accessed since this unix timestamp in seconds
+    NotAccessedSince(i64),
+    /// Removes all media of the dates in the range
+    DateRange(ApodDateRange),
+}
+
+impl EvictionPolicy {
+    /// The media to remove, least recently accessed first
+    pub fn select(&self, mut media: Vec<CachedMedia>) -> Vec<CachedMedia> {
+        media.sort_by_key(|cached| (cached.last_access, cached.date));
+        match self {
+            EvictionPolicy::MaxBytes(max_bytes) => {
+                let mut total: u64 = media.iter().map(|cached| cached.size).sum();
+                media
+                    .into_iter()
+                    .take_while(|cached| {
+                        let evict = total > *max_bytes;
+                        total = total.saturating_sub(cached.size);
+                        evict
+                    })
+                    .collect()
+            }
+            EvictionPolicy::NotAccessedSince(timestamp) => media
+                .into_iter()
+                .filter(|cached| cached.last_access.is_none_or(|access| access < *timestamp))
+                .collect(),
+            EvictionPolicy::DateRange(range) => media
+                .into_iter()
+                .filter(|cached| range.contains(cached.date))
+                .collect(),
+        }
+    }
+}
+
 pub trait MediaCache {
-    fn store(
+    fn store_variant(
         &mut self,
         date: ApodDate,
+        variant: MediaVariant,
         data: &[u8],
         media_type: MediaType,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
-    fn get(
+    fn get_variant(
         &self,
         date: ApodDate,
+        variant: MediaVariant,
     ) -> Result<Option<MediaEntry>, Box<dyn std::error::Error + Send + Sync>>;
+    /// All variants cached for the date
+    fn variants(
+        &self,
+        date: ApodDate,
+    ) -> Result<Vec<MediaVariant>, Box<dyn std::error::Error + Send + Sync>>;
+    /// Everything in the cache, sorted by date and variant
+    fn list(&self) -> Result<Vec<CachedMedia>, Box<dyn std::error::Error + Send + Sync>>;
+    /// Returns whether the variant was cached
+    fn delete_variant(
+        &mut self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Like [`MediaCache::get_variant`], but without counting as an access, e.g. for maintenance
+    fn peek_variant(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        self.get_variant(date, variant)
+    }
+
+    fn contains(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.variants(date)?.contains(&variant))
+    }
+
+    /// Removes all variants of the date, returns how many were removed
+    fn delete(
+        &mut self,
+        date: ApodDate,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut deleted = 0;
+        for variant in self.variants(date)? {
+            if self.delete_variant(date, variant)? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Removes everything, returns how many files were removed
+    fn clear(&mut self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut deleted = 0;
+        for cached in self.list()? {
+            if self.delete_variant(cached.date, cached.variant)? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    fn stats(&self) -> Result<MediaCacheStats, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(MediaCacheStats::from_media(&self.list()?))
+    }
+
+    /// Persists what is only kept in memory, like the access times of reads
+    fn flush(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// Removes the media selected by the policy, returns how many files were removed
+    fn evict(
+        &mut self,
+        policy: EvictionPolicy,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut deleted = 0;
+        for cached in policy.select(self.list()?) {
+            if self.delete_variant(cached.date, cached.variant)? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Stores the media in the highest quality available
+    fn store(
+        &mut self,
+        date: ApodDate,
+        data: &[u8],
+        media_type: MediaType,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.store_variant(date, MediaVariant::Hd, data, media_type)
+    }
+
+    /// The best full size variant that is cached
+    fn get(
+        &self,
+        date: ApodDate,
+    ) -> Result<Option<MediaEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        for variant in MediaVariant::FULL_SIZE {
+            if let Some(entry) = self.get_variant(date, variant)? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The preview of exactly this size, see [`MediaVariant::Thumbnail`]
+    fn get_thumbnail(
+        &self,
+        date: ApodDate,
+        size: u16,
+    ) -> Result<Option<MediaEntry>, Box<dyn std::error::Error + Send + Sync>> {
+        self.get_variant(date, MediaVariant::Thumbnail(size))
+    }
 }
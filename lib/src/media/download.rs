@@ -0,0 +1,260 @@
+use crate::archiving::{Archive, ArchiveError};
+use crate::client::ApodClient;
+use crate::date::range::ApodDateRange;
+use crate::date::{ApodDate, CurrentDateMode};
+use crate::media::{MediaCache, MediaVariant};
+use crate::ApodEntry;
+use futures_util::stream::{self, StreamExt};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    #[error("Archive error: {0}")]
+    Archive(#[from] ArchiveError),
+    #[error("Cache error: {0}")]
+    Cache(Box<dyn Error + Send + Sync>),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadOptions {
+    pub range: ApodDateRange,
+    /// Only full size variants can be downloaded
+    pub variant: MediaVariant,
+    /// Skips videos and other media that can't be shown as an image
+    pub images_only: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            range: ApodDateRange::till_current(CurrentDateMode::default()),
+            variant: MediaVariant::Hd,
+            images_only: true,
+        }
+    }
+}
+
+/// The persisted part of a [`BulkDownload`], enough to resume it after a restart
+#[derive(Debug, Default, Clone, PartialEq, Eq, bitcode::Encode, bitcode::Decode)]
+pub struct DownloadQueue {
+    /// Media still to download, including the ones in flight when the queue was saved
+    pub pending: Vec<(ApodDate, MediaVariant)>,
+    pub completed: usize,
+    pub bytes: u64,
+    /// Error message per media, moved back to pending by [`BulkDownload::retry_failed`]
+    pub failures: BTreeMap<(ApodDate, MediaVariant), String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub completed: usize,
+    pub failed: usize,
+    pub remaining: usize,
+    pub bytes: u64,
+    /// Estimated from the throughput of the current run, None before the first file finished
+    pub eta: Option<Duration>,
+}
+
+impl DownloadProgress {
+    pub fn total(&self) -> usize {
+        self.completed + self.failed + self.remaining
+    }
+
+    pub fn fraction(&self) -> f32 {
+        match self.total() {
+            0 => 1.0,
+            total => (self.completed + self.failed) as f32 / total as f32,
+        }
+    }
+}
+
+/// The cache is flushed after this many stored files and when the job finishes
+const FLUSH_INTERVAL: usize = 100;
+
+/// Downloads the media of many entries into a cache.
+///
+/// Requests go through the client, so its rate limiter applies no matter the concurrency.
+/// With a state file the queue is saved after every download, so an interrupted job can be
+/// loaded and continued where it stopped.
+pub struct BulkDownload {
+    queue: DownloadQueue,
+    state_file: Option<PathBuf>,
+    concurrency: usize,
+}
+
+impl BulkDownload {
+    /// Queues all entries in the range that match the options and are not cached yet
+    pub fn plan<M: MediaCache + ?Sized>(
+        entries: &Archive<ApodEntry>,
+        cache: &M,
+        options: &DownloadOptions,
+    ) -> Result<Self, DownloadError> {
+        let mut pending = Vec::new();
+        for entry in entries.iter_range(options.range) {
+            let is_image = entry.media.kind().is_some_and(|kind| kind.is_image());
+            if entry.media.variant_url(options.variant).is_none()
+                || (options.images_only && !is_image)
+            {
+                continue;
+            }
+
+            let cached = cache
+                .contains(entry.date, options.variant)
+                .map_err(DownloadError::Cache)?;
+            if !cached {
+                pending.push((entry.date, options.variant));
+            }
+        }
+        pending.sort();
+
+        Ok(Self::from_queue(DownloadQueue {
+            pending,
+            ..Default::default()
+        }))
+    }
+
+    pub fn from_queue(queue: DownloadQueue) -> Self {
+        Self {
+            queue,
+            state_file: None,
+            concurrency: 1,
+        }
+    }
+
+    /// Continues a saved job, which keeps saving to the same file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ArchiveError> {
+        let queue: DownloadQueue = bitcode::decode(&std::fs::read(&path)?)?;
+        Ok(Self::from_queue(queue).with_state_file(path))
+    }
+
+    pub fn with_state_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Downloads in flight at once, at least one
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn queue(&self) -> &DownloadQueue {
+        &self.queue
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.queue.pending.is_empty()
+    }
+
+    /// Queues the failed media again, returns how many
+    pub fn retry_failed(&mut self) -> usize {
+        let failed = std::mem::take(&mut self.queue.failures);
+        let count = failed.len();
+        self.enqueue(failed.into_keys());
+        count
+    }
+
+    /// Queues more media, e.g. to replace corrupt files. Only full size variants can be downloaded.
+    pub fn enqueue(&mut self, media: impl IntoIterator<Item = (ApodDate, MediaVariant)>) {
+        self.queue.pending.extend(media);
+        self.queue.pending.sort();
+        self.queue.pending.dedup();
+    }
+
+    /// Writes the queue to the state file, if there is one.
+    /// Writes next to it first, so an interrupted save keeps the previous queue intact.
+    pub fn save(&self) -> Result<(), DownloadError> {
+        if let Some(path) = &self.state_file {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut temp_path = path.as_os_str().to_owned();
+            temp_path.push(".part");
+            std::fs::write(&temp_path, bitcode::encode(&self.queue))?;
+            std::fs::rename(temp_path, path)?;
+        }
+        Ok(())
+    }
+
+    pub fn progress(&self) -> DownloadProgress {
+        DownloadProgress {
+            completed: self.queue.completed,
+            failed: self.queue.failures.len(),
+            remaining: self.queue.pending.len(),
+            bytes: self.queue.bytes,
+            eta: None,
+        }
+    }
+
+    /// Downloads everything pending, reporting progress after every file.
+    /// Failed downloads are recorded and skipped, only cache and state file errors stop the job.
+    pub async fn run<C, M>(
+        &mut self,
+        client: &C,
+        cache: &mut M,
+        entries: &Archive<ApodEntry>,
+        mut on_progress: impl FnMut(&DownloadProgress) + Send,
+    ) -> Result<DownloadProgress, DownloadError>
+    where
+        C: ApodClient + Sync,
+        M: MediaCache + ?Sized,
+    {
+        let started = Instant::now();
+        let mut processed = 0;
+
+        let media = self.queue.pending.clone();
+        let mut downloads = stream::iter(media)
+            .map(|(date, variant)| async move {
+                let result = match entries.get(date) {
+                    Some(entry) => client
+                        .fetch_media_variant(entry, variant)
+                        .await
+                        .map_err(|err| err.to_string()),
+                    None => Err("No entry for this date".to_string()),
+                };
+                ((date, variant), result)
+            })
+            .buffer_unordered(self.concurrency);
+
+        while let Some(((date, variant), result)) = downloads.next().await {
+            if let Ok(index) = self.queue.pending.binary_search(&(date, variant)) {
+                self.queue.pending.remove(index);
+            }
+            match result {
+                Ok(Some(media)) => {
+                    cache
+                        .store_variant(date, variant, &media.data, media.media_type)
+                        .map_err(DownloadError::Cache)?;
+                    self.queue.completed += 1;
+                    self.queue.bytes += media.data.len() as u64;
+                    if self.queue.completed.is_multiple_of(FLUSH_INTERVAL) {
+                        cache.flush().map_err(DownloadError::Cache)?;
+                    }
+                }
+                Ok(None) => {
+                    self.queue
+                        .failures
+                        .insert((date, variant), "Media not found".to_string());
+                }
+                Err(err) => {
+                    self.queue.failures.insert((date, variant), err);
+                }
+            }
+            self.save()?;
+            processed += 1;
+
+            let mut progress = self.progress();
+            let per_file = started.elapsed() / processed;
+            progress.eta = Some(per_file * progress.remaining as u32);
+            on_progress(&progress);
+        }
+
+        cache.flush().map_err(DownloadError::Cache)?;
+        Ok(self.progress())
+    }
+}
@@ -0,0 +1,435 @@
+use crate::date::ApodDate;
+use crate::media::{
+    media_file_name, parse_media_file_name, CachedMedia, ContentHash, EvictionPolicy, MediaCache,
+    MediaEntry, MediaType, MediaVariant,
+};
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const INDEX_HEADER: &str = "# apodex media index v1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexEntry {
+    media_type: MediaType,
+    hash: ContentHash,
+    last_access: Option<i64>,
+}
+
+type Index = BTreeMap<(ApodDate, MediaVariant), IndexEntry>;
+
+/// Stores media as plain files like `2005/03/ap050304-hd.jpg`, so the collection can be browsed,
+/// backed up and synced with ordinary tools, and used directly as the media directory of exports.
+///
+/// The media type, SHA-256 checksum, size and last access of every file are kept in a tab separated
+/// sidecar index at the root. Stores append a line to it, removals and [`MediaCache::flush`] rewrite it
+/// without the superseded lines, which also persists the access times of reads.
+/// Reads are verified against the checksum, files changed by hand fail until the index is rebuilt.
+#[derive(Clone)]
+pub struct FsMediaCache {
+    root: PathBuf,
+    index: Arc<Mutex<Index>>,
+}
+
+impl FsMediaCache {
+    pub const INDEX_FILE: &'static str = "index.tsv";
+
+    /// Opens the directory, rebuilding the index from the files if there is none
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+
+        let index_path = root.join(Self::INDEX_FILE);
+        let has_index = index_path.exists();
+        let index = if has_index {
+            let (index, compact) = read_index(&index_path)?;
+            if !compact {
+                write_index(&index_path, &index)?;
+            }
+            index
+        } else {
+            Index::new()
+        };
+
+        let cache = Self {
+            root,
+            index: Arc::new(Mutex::new(index)),
+        };
+        if !has_index {
+            cache.rebuild_index()?;
+        }
+        Ok(cache)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The file of the variant, if it is cached
+    pub fn path(&self, date: ApodDate, variant: MediaVariant) -> Option<PathBuf> {
+        let index = self.index.lock().unwrap();
+        let entry = index.get(&(date, variant))?;
+        Some(
+            self.root
+                .join(relative_path(date, variant, entry.media_type)),
+        )
+    }
+
+    /// The checksum and size of the file when it was stored
+    pub fn content_hash(&self, date: ApodDate, variant: MediaVariant) -> Option<ContentHash> {
+        let index = self.index.lock().unwrap();
+        index.get(&(date, variant)).map(|entry| entry.hash)
+    }
+
+    /// Scans the directory tree for media files named like [`media_file_name`],
+    /// e.g. after files were added or removed by hand. Returns the number of indexed files.
+    pub fn rebuild_index(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let mut index = self.index.lock().unwrap();
+        let mut rebuilt = Index::new();
+
+        let mut pending = vec![self.root.clone()];
+        while let Some(dir) = pending.pop() {
+            for dir_entry in fs::read_dir(&dir)? {
+                let path = dir_entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+
+                let Some((date, variant)) = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(parse_media_file_name)
+                else {
+                    continue;
+                };
+
+                // The path is derived from the type, other spellings like `.jpeg` can't be found again
+                let extension = path.extension().and_then(|ext| ext.to_str());
+                let media_type = MediaType::from_extension(extension.unwrap_or_default());
+                if extension != Some(media_type.extension()) {
+                    continue;
+                }
+
+                let data = fs::read(&path)?;
+                let last_access = index
+                    .get(&(date, variant))
+                    .and_then(|entry| entry.last_access);
+                rebuilt.insert(
+                    (date, variant),
+                    IndexEntry {
+                        media_type,
+                        hash: ContentHash::of(&data),
+                        last_access,
+                    },
+                );
+            }
+        }
+
+        *index = rebuilt;
+        write_index(&self.root.join(Self::INDEX_FILE), &index)?;
+        Ok(index.len())
+    }
+
+    /// Reads the file and verifies it against the index
+    fn read(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, Box<dyn Error + Send + Sync>> {
+        let mut index = self.index.lock().unwrap();
+        let Some(IndexEntry {
+            media_type, hash, ..
+        }) = index.get(&(date, variant)).cloned()
+        else {
+            return Ok(None);
+        };
+
+        let path = self.root.join(relative_path(date, variant, media_type));
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            // Removed by hand, forget it until the index is written again
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                index.remove(&(date, variant));
+                return Ok(None);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        hash.verify(date, variant, &data)?;
+        Ok(Some(MediaEntry { media_type, data }))
+    }
+
+    /// Removes the files and their index entries, writing the index once
+    fn remove(
+        &self,
+        keys: &[(ApodDate, MediaVariant)],
+    ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let mut index = self.index.lock().unwrap();
+        let mut removed = 0;
+        for (date, variant) in keys {
+            let Some(entry) = index.remove(&(*date, *variant)) else {
+                continue;
+            };
+            remove_file(
+                &self
+                    .root
+                    .join(relative_path(*date, *variant, entry.media_type)),
+            )?;
+            removed += 1;
+        }
+        write_index(&self.root.join(Self::INDEX_FILE), &index)?;
+        Ok(removed)
+    }
+}
+
+impl MediaCache for FsMediaCache {
+    fn store_variant(
+        &mut self,
+        date: ApodDate,
+        variant: MediaVariant,
+        data: &[u8],
+        media_type: MediaType,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut index = self.index.lock().unwrap();
+
+        // A previous file of another type has a different extension
+        if let Some(previous) = index.get(&(date, variant))
+            && previous.media_type != media_type
+        {
+            remove_file(
+                &self
+                    .root
+                    .join(relative_path(date, variant, previous.media_type)),
+            )?;
+        }
+
+        let path = self.root.join(relative_path(date, variant, media_type));
+        write_atomic(&path, data)?;
+
+        let entry = IndexEntry {
+            media_type,
+            hash: ContentHash::of(data),
+            last_access: Some(Utc::now().timestamp()),
+        };
+        append_index_line(&self.root.join(Self::INDEX_FILE), date, variant, &entry)?;
+        index.insert((date, variant), entry);
+        Ok(())
+    }
+
+    fn get_variant(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, Box<dyn Error + Send + Sync>> {
+        let entry = self.read(date, variant)?;
+        if entry.is_some()
+            && let Some(indexed) = self.index.lock().unwrap().get_mut(&(date, variant))
+        {
+            indexed.last_access = Some(Utc::now().timestamp());
+        }
+        Ok(entry)
+    }
+
+    fn peek_variant(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, Box<dyn Error + Send + Sync>> {
+        self.read(date, variant)
+    }
+
+    fn variants(&self, date: ApodDate) -> Result<Vec<MediaVariant>, Box<dyn Error + Send + Sync>> {
+        let index = self.index.lock().unwrap();
+        Ok(index
+            .keys()
+            .filter(|(key_date, _)| *key_date == date)
+            .map(|(_, variant)| *variant)
+            .collect())
+    }
+
+    fn list(&self) -> Result<Vec<CachedMedia>, Box<dyn Error + Send + Sync>> {
+        let index = self.index.lock().unwrap();
+        Ok(index
+            .iter()
+            .map(|((date, variant), entry)| CachedMedia {
+                date: *date,
+                variant: *variant,
+                media_type: entry.media_type,
+                size: entry.hash.size,
+                last_access: entry.last_access,
+            })
+            .collect())
+    }
+
+    fn delete_variant(
+        &mut self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(self.remove(&[(date, variant)])? > 0)
+    }
+
+    fn contains(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(self.index.lock().unwrap().contains_key(&(date, variant)))
+    }
+
+    fn clear(&mut self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let keys: Vec<_> = self.index.lock().unwrap().keys().copied().collect();
+        self.remove(&keys)
+    }
+
+    fn evict(&mut self, policy: EvictionPolicy) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let keys: Vec<_> = policy
+            .select(self.list()?)
+            .into_iter()
+            .map(|cached| (cached.date, cached.variant))
+            .collect();
+        self.remove(&keys)
+    }
+
+    /// Writes the index, persisting access times of reads since the last change
+    fn flush(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let index = self.index.lock().unwrap();
+        write_index(&self.root.join(Self::INDEX_FILE), &index)?;
+        Ok(())
+    }
+}
+
+/// Like `2005/03/ap050304-hd.jpg`
+fn relative_path(date: ApodDate, variant: MediaVariant, media_type: MediaType) -> PathBuf {
+    PathBuf::from(date.format("%Y"))
+        .join(date.format("%m"))
+        .join(media_file_name(date, variant, media_type))
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Writes next to the target first, so an interrupted write never leaves a truncated file.
+/// The temporary name doesn't parse as a media file, so leftovers are never indexed.
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".part");
+    fs::write(&temp_path, data)?;
+    fs::rename(temp_path, path)
+}
+
+/// Also returns whether the index is compact, i.e. has no lines that stores appended
+/// for media indexed before and no line left incomplete by an interrupted store
+fn read_index(path: &Path) -> io::Result<(Index, bool)> {
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid media index line '{line}'"),
+        )
+    };
+
+    let contents = fs::read_to_string(path)?;
+    let mut lines: Vec<&str> = contents.lines().collect();
+    let complete = contents.is_empty() || contents.ends_with('\n');
+    if !complete {
+        lines.pop();
+    }
+
+    let mut index = Index::new();
+    let mut count = 0;
+    for line in lines {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [file, media_type, checksum, size, last_access] = fields[..] else {
+            return Err(invalid(line));
+        };
+
+        let name = Path::new(file).file_name().and_then(|name| name.to_str());
+        let key = name
+            .and_then(parse_media_file_name)
+            .ok_or_else(|| invalid(line))?;
+        let entry = IndexEntry {
+            media_type: MediaType::from_extension(media_type),
+            hash: ContentHash {
+                sha256: parse_checksum(checksum).ok_or_else(|| invalid(line))?,
+                size: size.parse().map_err(|_| invalid(line))?,
+            },
+            last_access: match last_access {
+                "-" => None,
+                timestamp => Some(timestamp.parse().map_err(|_| invalid(line))?),
+            },
+        };
+        index.insert(key, entry);
+        count += 1;
+    }
+    let compact = complete && count == index.len();
+    Ok((index, compact))
+}
+
+fn write_index(path: &Path, index: &Index) -> io::Result<()> {
+    let mut contents = format!("{INDEX_HEADER}\n");
+    for ((date, variant), entry) in index {
+        contents.push_str(&index_line(*date, *variant, entry));
+    }
+    write_atomic(path, contents.as_bytes())
+}
+
+/// Later lines of the same media replace earlier ones when the index is read
+fn append_index_line(
+    path: &Path,
+    date: ApodDate,
+    variant: MediaVariant,
+    entry: &IndexEntry,
+) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?;
+    file.write_all(index_line(date, variant, entry).as_bytes())
+}
+
+fn index_line(date: ApodDate, variant: MediaVariant, entry: &IndexEntry) -> String {
+    let file = relative_path(date, variant, entry.media_type);
+    let last_access = entry
+        .last_access
+        .map(|timestamp| timestamp.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    format!(
+        "{}\t{}\t{}\t{}\t{}\n",
+        file.to_string_lossy().replace('\\', "/"),
+        entry.media_type.extension(),
+        format_checksum(&entry.hash.sha256),
+        entry.hash.size,
+        last_access
+    )
+}
+
+fn format_checksum(checksum: &[u8; 32]) -> String {
+    checksum.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn parse_checksum(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut checksum = [0; 32];
+    for (i, byte) in checksum.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(checksum)
+}
@@ -1,14 +1,52 @@
 use crate::date::ApodDate;
-use crate::media::{MediaCache, MediaEntry, MediaType};
-use heed::byteorder::NativeEndian;
-use heed::types::{Bytes, I32};
-use heed::EnvOpenOptions;
+use crate::media::{
+    CachedMedia, ContentHash, EvictionPolicy, MediaCache, MediaEntry, MediaType, MediaVariant,
+};
+use chrono::Utc;
+use heed::types::Bytes;
+use heed::{EnvOpenOptions, MdbError};
+use std::collections::HashMap;
 use std::error::Error;
 use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
 
+const THUMBNAIL_TAG: u8 = 1;
+const STANDARD_TAG: u8 = 2;
+const POSTER_TAG: u8 = 3;
+
+const MB: usize = 1024 * 1024;
+
+/// Stored next to every media entry, so listing the cache doesn't have to decode the media
+#[derive(bitcode::Encode, bitcode::Decode)]
+struct MediaInfo {
+    media_type: MediaType,
+    last_access: Option<i64>,
+}
+
+/// HD media is keyed by the native endian day counter, as all media was before variants,
+/// back then always the highest quality available.
+/// Other variants are keyed by the big endian day counter followed by a variant tag,
+/// so all variants of a date share a prefix.
+///
+/// Every entry is verified against the [`ContentHash`] stored with it when read,
+/// entries cached before hashes were stored are read unverified until [`HeedMediaCache::backfill_hashes`].
+///
+/// When the memory map is full it grows up to [`HeedMediaCache::max_size_mb`],
+/// beyond that the least recently accessed media is evicted.
+///
+/// Access times of reads are kept in memory and written in one transaction
+/// with the next store or [`MediaCache::flush`].
+#[derive(Clone)]
 pub struct HeedMediaCache {
     env: heed::Env,
-    db: heed::Database<I32<NativeEndian>, Bytes>,
+    db: heed::Database<Bytes, Bytes>,
+    info_db: heed::Database<Bytes, Bytes>,
+    hash_db: heed::Database<Bytes, Bytes>,
+    max_size_mb: usize,
+    /// Held for writing while resizing the map, which requires that no transactions are active
+    resize_lock: Arc<RwLock<()>>,
+    /// Unix timestamps of reads that are not written yet, by key
+    accesses: Arc<Mutex<HashMap<Vec<u8>, i64>>>,
 }
 
 impl HeedMediaCache {
@@ -21,50 +59,392 @@ impl HeedMediaCache {
 
         let env = unsafe {
             EnvOpenOptions::new()
-                .map_size(max_size_mb * 1024 * 1024)
-                .max_dbs(1)
+                .map_size(max_size_mb * MB)
+                .max_dbs(3)
                 .open(dir)?
         };
 
-        let db = {
+        let (db, info_db, hash_db) = {
             let mut txn = env.write_txn()?;
             let db = env.create_database(&mut txn, Some(name.as_ref()))?;
+            let info_name = format!("{}-info", name.as_ref());
+            let info_db = env.create_database(&mut txn, Some(&info_name))?;
+            let hash_name = format!("{}-hash", name.as_ref());
+            let hash_db = env.create_database(&mut txn, Some(&hash_name))?;
             txn.commit()?;
-            db
+            (db, info_db, hash_db)
+        };
+
+        Ok(Self {
+            env,
+            db,
+            info_db,
+            hash_db,
+            max_size_mb,
+            resize_lock: Arc::new(RwLock::new(())),
+            accesses: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Lets the map grow beyond its initial size when it is full, before evicting any media
+    pub fn with_max_size_mb(mut self, max_size_mb: usize) -> Self {
+        self.max_size_mb = max_size_mb.max(self.map_size() / MB);
+        self
+    }
+
+    pub fn max_size_mb(&self) -> usize {
+        self.max_size_mb
+    }
+
+    /// The current size of the memory map in bytes
+    pub fn map_size(&self) -> usize {
+        self.env.info().map_size
+    }
+
+    /// The size of the database file in bytes, which never shrinks
+    pub fn disk_size(&self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        Ok(self.env.real_disk_size()?)
+    }
+
+    /// Bytes in use, excluding pages that were freed and will be reused
+    pub fn used_size(&self) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let _guard = self.resize_lock.read().unwrap();
+        Ok(self.env.non_free_pages_size()?)
+    }
+
+    fn key(date: ApodDate, variant: MediaVariant) -> Vec<u8> {
+        let mut key = Self::variant_prefix(date).to_vec();
+        match variant {
+            MediaVariant::Hd => return date.days().to_ne_bytes().to_vec(),
+            MediaVariant::Standard => key.push(STANDARD_TAG),
+            MediaVariant::Thumbnail(size) => {
+                key.push(THUMBNAIL_TAG);
+                key.extend_from_slice(&size.to_be_bytes());
+            }
+            MediaVariant::Poster => key.push(POSTER_TAG),
+        }
+        key
+    }
+
+    fn variant_prefix(date: ApodDate) -> [u8; 4] {
+        date.days().to_be_bytes()
+    }
+
+    fn parse_variant(suffix: &[u8]) -> Option<MediaVariant> {
+        match suffix {
+            [STANDARD_TAG] => Some(MediaVariant::Standard),
+            [THUMBNAIL_TAG, a, b] => Some(MediaVariant::Thumbnail(u16::from_be_bytes([*a, *b]))),
+            [POSTER_TAG] => Some(MediaVariant::Poster),
+            _ => None,
+        }
+    }
+
+    fn parse_key(key: &[u8]) -> Option<(ApodDate, MediaVariant)> {
+        let (prefix, suffix) = key.split_first_chunk::<4>()?;
+        if suffix.is_empty() {
+            let date = ApodDate::from_days(i32::from_ne_bytes(*prefix));
+            return Some((date, MediaVariant::Hd));
+        }
+        let date = ApodDate::from_days(i32::from_be_bytes(*prefix));
+        Some((date, Self::parse_variant(suffix)?))
+    }
+
+    fn put(
+        &self,
+        key: &[u8],
+        entry_bytes: &[u8],
+        info_bytes: &[u8],
+        hash: &ContentHash,
+    ) -> heed::Result<()> {
+        let _guard = self.resize_lock.read().unwrap();
+        let mut txn = self.env.write_txn()?;
+        self.db.put(&mut txn, key, entry_bytes)?;
+        self.info_db.put(&mut txn, key, info_bytes)?;
+        self.hash_db.put(&mut txn, key, &hash.to_bytes())?;
+        txn.commit()
+    }
+
+    /// Decodes the entry and verifies it against its hash, if one is stored
+    fn read(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, Box<dyn Error + Send + Sync>> {
+        let key = Self::key(date, variant);
+        let _guard = self.resize_lock.read().unwrap();
+        let txn = self.env.read_txn()?;
+        let Some(entry_bytes) = self.db.get(&txn, &key)? else {
+            return Ok(None);
         };
+        let entry: MediaEntry = bitcode::decode(entry_bytes)?;
 
-        Ok(Self { env, db })
+        if let Some(hash) = self
+            .hash_db
+            .get(&txn, &key)?
+            .and_then(ContentHash::from_bytes)
+        {
+            hash.verify(date, variant, &entry.data)?;
+        }
+        Ok(Some(entry))
+    }
+
+    /// The hash stored with the entry, None for entries cached before hashes were stored
+    pub fn content_hash(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<ContentHash>, Box<dyn Error + Send + Sync>> {
+        let _guard = self.resize_lock.read().unwrap();
+        let txn = self.env.read_txn()?;
+        let hash = self.hash_db.get(&txn, &Self::key(date, variant))?;
+        Ok(hash.and_then(ContentHash::from_bytes))
+    }
+
+    /// Stores hashes for entries cached before hashes were, returns how many.
+    /// Run this after verifying the cache, the current data is assumed to be intact.
+    pub fn backfill_hashes(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let _guard = self.resize_lock.read().unwrap();
+        let mut txn = self.env.write_txn()?;
+
+        let mut hashes = Vec::new();
+        for result in self.db.iter(&txn)? {
+            let (key, entry_bytes) = result?;
+            if Self::parse_key(key).is_some() && self.hash_db.get(&txn, key)?.is_none() {
+                let entry: MediaEntry = bitcode::decode(entry_bytes)?;
+                hashes.push((key.to_vec(), ContentHash::of(&entry.data)));
+            }
+        }
+
+        for (key, hash) in &hashes {
+            self.hash_db.put(&mut txn, key, &hash.to_bytes())?;
+        }
+        txn.commit()?;
+        Ok(hashes.len())
+    }
+
+    /// Doubles the map size, up to the maximum size. Returns false if it can't grow any further.
+    fn grow(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let _guard = self.resize_lock.write().unwrap();
+        let current = self.map_size();
+        let grown = (current * 2).min(self.max_size_mb * MB);
+        if grown <= current {
+            return Ok(false);
+        }
+        unsafe { self.env.resize(grown)? };
+        Ok(true)
+    }
+
+    fn write_accesses(&self, accesses: &HashMap<Vec<u8>, i64>) -> heed::Result<()> {
+        let _guard = self.resize_lock.read().unwrap();
+        let mut txn = self.env.write_txn()?;
+        for (key, last_access) in accesses {
+            // Media deleted since it was read has no info to update
+            let media_type = match self.info_db.get(&txn, key)? {
+                Some(info_bytes) => bitcode::decode::<MediaInfo>(info_bytes)
+                    .ok()
+                    .map(|info| info.media_type),
+                None => self
+                    .db
+                    .get(&txn, key)?
+                    .and_then(|entry_bytes| bitcode::decode::<MediaEntry>(entry_bytes).ok())
+                    .map(|entry| entry.media_type),
+            };
+            let Some(media_type) = media_type else {
+                continue;
+            };
+            let info = MediaInfo {
+                media_type,
+                last_access: Some(*last_access),
+            };
+            self.info_db.put(&mut txn, key, &bitcode::encode(&info))?;
+        }
+        txn.commit()
     }
 }
 
 impl MediaCache for HeedMediaCache {
-    fn store(
+    fn store_variant(
         &mut self,
         date: ApodDate,
+        variant: MediaVariant,
         data: &[u8],
         media_type: MediaType,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let key = date.days();
+        let key = Self::key(date, variant);
+        self.accesses.lock().unwrap().remove(&key);
 
         let entry = MediaEntry {
             media_type,
             data: data.to_vec(),
         };
         let entry_bytes = bitcode::encode(&entry);
+        let hash = ContentHash::of(data);
+        // Storing counts as an access, so new media isn't the first to be evicted
+        let info_bytes = bitcode::encode(&MediaInfo {
+            media_type,
+            last_access: Some(Utc::now().timestamp()),
+        });
 
-        let mut txn = self.env.write_txn()?;
-        self.db.put(&mut txn, &key, entry_bytes.as_slice())?;
-        txn.commit()?;
+        loop {
+            match self.put(&key, &entry_bytes, &info_bytes, &hash) {
+                Err(heed::Error::Mdb(MdbError::MapFull)) => {
+                    if self.grow()? {
+                        continue;
+                    }
+                    // Twice the entry size leaves room for the pages LMDB needs besides the data
+                    let target = self
+                        .stats()?
+                        .total_bytes
+                        .saturating_sub(2 * entry_bytes.len() as u64);
+                    if self.evict(EvictionPolicy::MaxBytes(target))? == 0 {
+                        return Err(heed::Error::Mdb(MdbError::MapFull).into());
+                    }
+                }
+                Err(err) => return Err(err.into()),
+                Ok(()) => break,
+            }
+        }
+
+        // Failing to track earlier accesses should never fail the store itself
+        let _ = self.flush();
         Ok(())
     }
 
-    fn get(&self, date: ApodDate) -> Result<Option<MediaEntry>, Box<dyn Error + Send + Sync>> {
-        let key = date.days();
-        let txn = self.env.read_txn()?;
-        let Some(entry_bytes) = self.db.get(&txn, &key)? else {
+    fn get_variant(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, Box<dyn Error + Send + Sync>> {
+        let Some(entry) = self.read(date, variant)? else {
             return Ok(None);
         };
-        let entry: MediaEntry = bitcode::decode(entry_bytes)?;
+        self.accesses
+            .lock()
+            .unwrap()
+            .insert(Self::key(date, variant), Utc::now().timestamp());
         Ok(Some(entry))
     }
+
+    fn peek_variant(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<Option<MediaEntry>, Box<dyn Error + Send + Sync>> {
+        self.read(date, variant)
+    }
+
+    fn variants(&self, date: ApodDate) -> Result<Vec<MediaVariant>, Box<dyn Error + Send + Sync>> {
+        let _guard = self.resize_lock.read().unwrap();
+        let txn = self.env.read_txn()?;
+        let mut variants = Vec::new();
+
+        if self
+            .db
+            .get(&txn, &Self::key(date, MediaVariant::Hd))?
+            .is_some()
+        {
+            variants.push(MediaVariant::Hd);
+        }
+
+        let prefix = Self::variant_prefix(date);
+        for result in self.db.prefix_iter(&txn, &prefix)? {
+            let (key, _) = result?;
+            // Legacy keys of other dates can share the prefix, those are exactly 4 bytes long
+            if let Some(variant) = Self::parse_variant(&key[prefix.len()..]) {
+                variants.push(variant);
+            }
+        }
+
+        variants.sort();
+        Ok(variants)
+    }
+
+    fn list(&self) -> Result<Vec<CachedMedia>, Box<dyn Error + Send + Sync>> {
+        let accesses = self.accesses.lock().unwrap().clone();
+        let _guard = self.resize_lock.read().unwrap();
+        let txn = self.env.read_txn()?;
+        let mut media = Vec::new();
+
+        for result in self.db.iter(&txn)? {
+            let (key, entry_bytes) = result?;
+            let Some((date, variant)) = Self::parse_key(key) else {
+                continue;
+            };
+
+            // Media cached before the info was tracked has to be decoded once
+            let info = match self.info_db.get(&txn, key)? {
+                Some(info_bytes) => bitcode::decode::<MediaInfo>(info_bytes)?,
+                None => MediaInfo {
+                    media_type: bitcode::decode::<MediaEntry>(entry_bytes)?.media_type,
+                    last_access: None,
+                },
+            };
+
+            media.push(CachedMedia {
+                date,
+                variant,
+                media_type: info.media_type,
+                size: entry_bytes.len() as u64,
+                last_access: accesses.get(key).copied().or(info.last_access),
+            });
+        }
+
+        media.sort_by_key(|cached| (cached.date, cached.variant));
+        Ok(media)
+    }
+
+    fn delete_variant(
+        &mut self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let key = Self::key(date, variant);
+        self.accesses.lock().unwrap().remove(&key);
+        let _guard = self.resize_lock.read().unwrap();
+        let mut txn = self.env.write_txn()?;
+        let deleted = self.db.delete(&mut txn, &key)?;
+        self.info_db.delete(&mut txn, &key)?;
+        self.hash_db.delete(&mut txn, &key)?;
+        txn.commit()?;
+        Ok(deleted)
+    }
+
+    fn contains(
+        &self,
+        date: ApodDate,
+        variant: MediaVariant,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let _guard = self.resize_lock.read().unwrap();
+        let txn = self.env.read_txn()?;
+        Ok(self.db.get(&txn, &Self::key(date, variant))?.is_some())
+    }
+
+    fn clear(&mut self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        self.accesses.lock().unwrap().clear();
+        let _guard = self.resize_lock.read().unwrap();
+        let mut txn = self.env.write_txn()?;
+        let count = self.db.len(&txn)? as usize;
+        self.db.clear(&mut txn)?;
+        self.info_db.clear(&mut txn)?;
+        self.hash_db.clear(&mut txn)?;
+        txn.commit()?;
+        Ok(count)
+    }
+
+    /// Writes the access times of reads since the last store in one transaction
+    fn flush(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let accesses = std::mem::take(&mut *self.accesses.lock().unwrap());
+        if accesses.is_empty() {
+            return Ok(());
+        }
+        if let Err(err) = self.write_accesses(&accesses) {
+            // Kept for the next flush, unless the media was read again in the meantime
+            let mut pending = self.accesses.lock().unwrap();
+            for (key, last_access) in accesses {
+                pending.entry(key).or_insert(last_access);
+            }
+            return Err(err.into());
+        }
+        Ok(())
+    }
 }
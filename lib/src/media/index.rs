@@ -0,0 +1,341 @@
+use crate::date::ApodDate;
+use crate::media::metadata::ImageMetadata;
+use crate::media::palette::ColorPalette;
+use crate::media::{MediaCache, MediaVariant};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MediaIndexError {
+    #[error("Cache error: {0}")]
+    Cache(Box<dyn Error + Send + Sync>),
+    #[error("Codec error: {0}")]
+    Codec(#[from] bitcode::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Difference hash of an image, similar images have hashes that differ in few bits.
+///
+/// Each bit tells whether a pixel of the image downscaled to 9x8 grayscale pixels
+/// is brighter than its right neighbor, so it survives rescaling, recompression and small edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, bitcode::Encode, bitcode::Decode)]
+pub struct PerceptualHash(pub u64);
+
+impl PerceptualHash {
+    pub const BITS: u32 = 64;
+
+    pub fn of_image(image: &DynamicImage) -> Self {
+        let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+        let mut hash = 0u64;
+        for y in 0..8 {
+            for x in 0..8 {
+                let left = small.get_pixel(x, y).0[0];
+                let right = small.get_pixel(x + 1, y).0[0];
+                hash = (hash << 1) | (left > right) as u64;
+            }
+        }
+        Self(hash)
+    }
+
+    pub fn of_data(data: &[u8]) -> Result<Self, image::ImageError> {
+        Ok(Self::of_image(&image::load_from_memory(data)?))
+    }
+
+    /// Number of differing bits, 0 for the same image
+    pub fn distance(&self, other: &Self) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+
+    /// Images without any horizontal gradient, like plain black frames, all hash to zero
+    /// and would match each other
+    pub fn is_meaningful(&self) -> bool {
+        self.0 != 0
+    }
+}
+
+/// Features of the cached image of one date
+#[derive(Debug, Clone, PartialEq, bitcode::Encode, bitcode::Decode)]
+pub struct IndexedImage {
+    /// The variant the hash and palette were computed from
+    pub variant: MediaVariant,
+    pub hash: PerceptualHash,
+    pub palette: ColorPalette,
+    /// Of the best cached full size variant, None if only thumbnails are cached
+    pub metadata: Option<ImageMetadata>,
+}
+
+/// Outcome of [`MediaIndex::update`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IndexUpdateReport {
+    /// Dates that were indexed or updated
+    pub indexed: usize,
+    /// Dates with cached media that could not be read, e.g. because it fails its hash
+    pub unreadable: usize,
+}
+
+/// Perceptual hashes, palettes and metadata of cached images, to find images that were shown
+/// more than once and to filter and sort entries by their images.
+///
+/// Built from whatever is cached, dates without cached images are missing until updated again.
+#[derive(Debug, Default, Clone, PartialEq, bitcode::Encode, bitcode::Decode)]
+pub struct MediaIndex {
+    images: BTreeMap<ApodDate, IndexedImage>,
+}
+
+impl MediaIndex {
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    pub fn get(&self, date: ApodDate) -> Option<&IndexedImage> {
+        self.images.get(&date)
+    }
+
+    pub fn insert(&mut self, date: ApodDate, image: IndexedImage) {
+        self.images.insert(date, image);
+    }
+
+    pub fn remove(&mut self, date: ApodDate) -> Option<IndexedImage> {
+        self.images.remove(&date)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ApodDate, &IndexedImage)> {
+        self.images.iter()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MediaIndexError> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, bitcode::encode(self))?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MediaIndexError> {
+        Ok(bitcode::decode(&std::fs::read(path)?)?)
+    }
+
+    /// Indexes cached images of dates that are not indexed yet, reads the metadata again
+    /// when a better full size variant was cached and forgets dates that are no longer cached.
+    ///
+    /// Hashes the smallest cached thumbnail when there is one, it hashes like the full image
+    /// but decodes much faster. Media that can't be read or decoded is skipped,
+    /// only failing to list the cache fails the update.
+    pub fn update<M: MediaCache + ?Sized>(
+        &mut self,
+        cache: &M,
+        mut on_progress: impl FnMut(usize, usize),
+    ) -> Result<IndexUpdateReport, MediaIndexError> {
+        let mut sources: BTreeMap<ApodDate, Sources> = BTreeMap::new();
+        for cached in cache.list().map_err(MediaIndexError::Cache)? {
+            if !cached.media_type.is_image() || cached.variant == MediaVariant::Poster {
+                continue;
+            }
+            let source = sources.entry(cached.date).or_insert(Sources {
+                hash: cached.variant,
+                metadata: None,
+            });
+            if hash_priority(cached.variant) < hash_priority(source.hash) {
+                source.hash = cached.variant;
+            }
+            if cached.variant.is_full_size()
+                && source
+                    .metadata
+                    .is_none_or(|current| full_size_rank(cached.variant) < full_size_rank(current))
+            {
+                source.metadata = Some(cached.variant);
+            }
+        }
+
+        self.images.retain(|date, _| sources.contains_key(date));
+        sources.retain(|date, source| match self.images.get(date) {
+            Some(image) => {
+                source.metadata.is_some()
+                    && image.metadata.as_ref().map(|metadata| metadata.variant) != source.metadata
+            }
+            None => true,
+        });
+
+        let total = sources.len();
+        let mut report = IndexUpdateReport::default();
+        for (i, (date, source)) in sources.into_iter().enumerate() {
+            on_progress(i, total);
+            let existing = self.images.get(&date);
+            let mut visual = existing.map(|image| Visual {
+                variant: image.variant,
+                hash: image.hash,
+                palette: image.palette.clone(),
+            });
+            let mut metadata = existing.and_then(|image| image.metadata.clone());
+            let mut unreadable = false;
+            let mut peek = |variant| {
+                cache.peek_variant(date, variant).unwrap_or_else(|_| {
+                    unreadable = true;
+                    None
+                })
+            };
+
+            if let Some(variant) = source.metadata
+                && let Some(entry) = peek(variant)
+            {
+                metadata = ImageMetadata::read(variant, &entry.data).ok();
+                if visual.is_none() && variant == source.hash {
+                    visual = Visual::of_data(variant, &entry.data);
+                }
+            }
+
+            if visual.is_none()
+                && let Some(entry) = peek(source.hash)
+            {
+                visual = Visual::of_data(source.hash, &entry.data);
+            }
+
+            if unreadable {
+                report.unreadable += 1;
+            }
+
+            if let Some(visual) = visual {
+                self.images.insert(
+                    date,
+                    IndexedImage {
+                        variant: visual.variant,
+                        hash: visual.hash,
+                        palette: visual.palette,
+                        metadata,
+                    },
+                );
+                report.indexed += 1;
+            }
+        }
+        on_progress(total, total);
+
+        Ok(report)
+    }
+
+    pub fn metadata(&self, date: ApodDate) -> Option<&ImageMetadata> {
+        self.get(date)?.metadata.as_ref()
+    }
+
+    /// Other dates with a similar image, the most similar first
+    pub fn similar(&self, date: ApodDate, max_distance: u32) -> Vec<(ApodDate, u32)> {
+        let Some(image) = self.get(date) else {
+            return Vec::new();
+        };
+        if !image.hash.is_meaningful() {
+            return Vec::new();
+        }
+
+        let mut similar: Vec<(ApodDate, u32)> = self
+            .images
+            .iter()
+            .filter(|(other_date, _)| **other_date != date)
+            .map(|(other_date, other)| (*other_date, image.hash.distance(&other.hash)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        similar.sort_by_key(|(other_date, distance)| (*distance, *other_date));
+        similar
+    }
+
+    /// Dates whose palettes are closest to the color, the closest first
+    pub fn closest_to_color(&self, rgb: [u8; 3], max_distance: f32) -> Vec<(ApodDate, f32)> {
+        let mut closest: Vec<(ApodDate, f32)> = self
+            .images
+            .iter()
+            .map(|(date, image)| (*date, image.palette.distance(rgb)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        closest.sort_by(|(a_date, a), (b_date, b)| a.total_cmp(b).then(a_date.cmp(b_date)));
+        closest
+    }
+
+    /// Groups of dates with similar images, each sorted by date and the groups by their first date.
+    /// Images are grouped transitively, so the ends of a group may differ more than the distance.
+    pub fn duplicate_groups(&self, max_distance: u32) -> Vec<Vec<ApodDate>> {
+        let images: Vec<(ApodDate, PerceptualHash)> = self
+            .images
+            .iter()
+            .filter(|(_, image)| image.hash.is_meaningful())
+            .map(|(date, image)| (*date, image.hash))
+            .collect();
+
+        // Union-find over the indices of the images
+        let mut parents: Vec<usize> = (0..images.len()).collect();
+        for i in 0..images.len() {
+            for j in i + 1..images.len() {
+                if images[i].1.distance(&images[j].1) <= max_distance {
+                    let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                    parents[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut groups: BTreeMap<usize, Vec<ApodDate>> = BTreeMap::new();
+        for (i, (date, _)) in images.iter().enumerate() {
+            let group = root(&mut parents, i);
+            groups.entry(group).or_default().push(*date);
+        }
+        groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect()
+    }
+}
+
+/// The representative of the set of the index, compressing the path on the way
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// What is computed from the decoded image
+struct Visual {
+    variant: MediaVariant,
+    hash: PerceptualHash,
+    palette: ColorPalette,
+}
+
+impl Visual {
+    fn of_data(variant: MediaVariant, data: &[u8]) -> Option<Self> {
+        let image = image::load_from_memory(data).ok()?;
+        Some(Self {
+            variant,
+            hash: PerceptualHash::of_image(&image),
+            palette: ColorPalette::of_image(&image),
+        })
+    }
+}
+
+/// The variants to index a date from
+struct Sources {
+    hash: MediaVariant,
+    /// The best full size variant
+    metadata: Option<MediaVariant>,
+}
+
+/// Lower is better
+fn full_size_rank(variant: MediaVariant) -> Option<usize> {
+    MediaVariant::FULL_SIZE
+        .iter()
+        .position(|full_size| *full_size == variant)
+}
+
+/// Lower is decoded faster, smaller thumbnails first
+fn hash_priority(variant: MediaVariant) -> (u8, u16) {
+    match variant {
+        MediaVariant::Thumbnail(size) => (0, size),
+        MediaVariant::Standard => (1, 0),
+        MediaVariant::Hd => (2, 0),
+        MediaVariant::Poster => (3, 0),
+    }
+}
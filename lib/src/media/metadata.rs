@@ -0,0 +1,234 @@
+use crate::media::{MediaType, MediaVariant};
+use exif::{In, Tag, Value};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, ColorType, ImageDecoder, ImageReader};
+use std::fmt::Display;
+use std::io::Cursor;
+
+/// Width and height of 4K UHD
+pub const UHD_4K: (u32, u32) = (3840, 2160);
+
+/// Properties of a cached image, read from its header without decoding the pixels,
+/// except for counting the frames of animations
+#[derive(Debug, Clone, PartialEq, bitcode::Encode, bitcode::Decode)]
+pub struct ImageMetadata {
+    /// The variant the metadata was read from
+    pub variant: MediaVariant,
+    pub media_type: MediaType,
+    pub width: u32,
+    pub height: u32,
+    pub file_size: u64,
+    pub color: ColorModel,
+    pub bits_per_channel: u8,
+    /// 1 for still images
+    pub frames: u32,
+    pub exif: Option<ExifData>,
+}
+
+impl ImageMetadata {
+    pub fn read(variant: MediaVariant, data: &[u8]) -> Result<Self, image::ImageError> {
+        let mut decoder = ImageReader::new(Cursor::new(data))
+            .with_guessed_format()?
+            .into_decoder()?;
+        let (width, height) = decoder.dimensions();
+        let color_type = decoder.color_type();
+        // Broken or unusual EXIF data shouldn't hide the rest
+        let exif = decoder
+            .exif_metadata()
+            .ok()
+            .flatten()
+            .and_then(ExifData::parse);
+
+        let media_type = MediaType::sniff(data).unwrap_or(MediaType::Other);
+        Ok(Self {
+            variant,
+            media_type,
+            width,
+            height,
+            file_size: data.len() as u64,
+            color: ColorModel::from(color_type),
+            bits_per_channel: (color_type.bits_per_pixel() / color_type.channel_count() as u16)
+                as u8,
+            frames: count_frames(media_type, data),
+            exif,
+        })
+    }
+
+    pub fn pixels(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    pub fn megapixels(&self) -> f32 {
+        self.pixels() as f32 / 1_000_000.0
+    }
+
+    /// More pixels than 4K UHD, in any orientation
+    pub fn is_larger_than_4k(&self) -> bool {
+        self.pixels() > UHD_4K.0 as u64 * UHD_4K.1 as u64
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames > 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, bitcode::Encode, bitcode::Decode)]
+pub enum ColorModel {
+    Grayscale,
+    GrayscaleAlpha,
+    Rgb,
+    Rgba,
+    Other,
+}
+
+impl From<ColorType> for ColorModel {
+    fn from(color_type: ColorType) -> Self {
+        match color_type {
+            ColorType::L8 | ColorType::L16 => ColorModel::Grayscale,
+            ColorType::La8 | ColorType::La16 => ColorModel::GrayscaleAlpha,
+            ColorType::Rgb8 | ColorType::Rgb16 | ColorType::Rgb32F => ColorModel::Rgb,
+            ColorType::Rgba8 | ColorType::Rgba16 | ColorType::Rgba32F => ColorModel::Rgba,
+            _ => ColorModel::Other,
+        }
+    }
+}
+
+impl Display for ColorModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorModel::Grayscale => write!(f, "Grayscale"),
+            ColorModel::GrayscaleAlpha => write!(f, "Grayscale with alpha"),
+            ColorModel::Rgb => write!(f, "RGB"),
+            ColorModel::Rgba => write!(f, "RGBA"),
+            ColorModel::Other => write!(f, "Other"),
+        }
+    }
+}
+
+/// The EXIF fields worth showing, formatted for display
+#[derive(Debug, Default, Clone, PartialEq, bitcode::Encode, bitcode::Decode)]
+pub struct ExifData {
+    /// Make and model
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+    /// Like `1/250 s`
+    pub exposure_time: Option<String>,
+    /// Like `f/2.8`
+    pub aperture: Option<String>,
+    pub iso: Option<u32>,
+    /// Like `50 mm`
+    pub focal_length: Option<String>,
+    /// As written by the camera, usually local time without zone
+    pub taken: Option<String>,
+    pub position: Option<GpsPosition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, bitcode::Encode, bitcode::Decode)]
+pub struct GpsPosition {
+    /// Degrees, negative south of the equator
+    pub latitude: f64,
+    /// Degrees, negative west of Greenwich
+    pub longitude: f64,
+}
+
+impl ExifData {
+    /// Parses raw EXIF data as embedded in JPEG, PNG and WebP files, None if nothing useful is in it
+    pub fn parse(raw: Vec<u8>) -> Option<Self> {
+        let exif = exif::Reader::new().read_raw(raw).ok()?;
+        let text = |tag: Tag| {
+            let field = exif.get_field(tag, In::PRIMARY)?;
+            let value = match &field.value {
+                // Often padded with spaces or extra empty strings
+                Value::Ascii(strings) => strings
+                    .iter()
+                    .map(|string| String::from_utf8_lossy(string).trim().to_string())
+                    .find(|string| !string.is_empty())?,
+                value => value.display_as(tag).to_string(),
+            };
+            (!value.is_empty()).then_some(value)
+        };
+        let with_unit = |tag: Tag| {
+            let field = exif.get_field(tag, In::PRIMARY)?;
+            Some(field.display_value().with_unit(&exif).to_string())
+        };
+
+        let camera = match (text(Tag::Make), text(Tag::Model)) {
+            // Models often repeat the make, like `Canon` and `Canon EOS 5D`
+            (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+            (Some(make), Some(model)) => Some(format!("{make} {model}")),
+            (make, model) => make.or(model),
+        };
+
+        let data = Self {
+            camera,
+            lens: text(Tag::LensModel),
+            exposure_time: with_unit(Tag::ExposureTime),
+            aperture: text(Tag::FNumber).map(|f_number| format!("f/{f_number}")),
+            iso: exif
+                .get_field(Tag::PhotographicSensitivity, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0)),
+            focal_length: with_unit(Tag::FocalLength),
+            taken: text(Tag::DateTimeOriginal).or_else(|| text(Tag::DateTime)),
+            position: gps_position(&exif),
+        };
+        (data != Self::default()).then_some(data)
+    }
+}
+
+fn gps_position(exif: &exif::Exif) -> Option<GpsPosition> {
+    let coordinate = |tag: Tag, ref_tag: Tag, negative: &[u8]| {
+        let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let [degrees, minutes, seconds] = parts.as_slice() else {
+            return None;
+        };
+        let value = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+        let is_negative = match &exif.get_field(ref_tag, In::PRIMARY)?.value {
+            Value::Ascii(refs) => refs
+                .first()
+                .and_then(|r| r.first())
+                .is_some_and(|r| negative.contains(r)),
+            _ => false,
+        };
+        value
+            .is_finite()
+            .then_some(if is_negative { -value } else { value })
+    };
+
+    Some(GpsPosition {
+        latitude: coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, b"S")?,
+        longitude: coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, b"W")?,
+    })
+}
+
+/// Decodes every frame of animations, still images are not decoded at all.
+/// Frames after a decoding error are not counted.
+fn count_frames(media_type: MediaType, data: &[u8]) -> u32 {
+    let frames = match media_type {
+        MediaType::ImageGIF => GifDecoder::new(Cursor::new(data))
+            .map(|decoder| decoder.into_frames().take_while(Result::is_ok).count()),
+        MediaType::ImagePNG => PngDecoder::new(Cursor::new(data)).and_then(|decoder| {
+            if decoder.is_apng()? {
+                Ok(decoder
+                    .apng()?
+                    .into_frames()
+                    .take_while(Result::is_ok)
+                    .count())
+            } else {
+                Ok(1)
+            }
+        }),
+        MediaType::ImageWEBP => WebPDecoder::new(Cursor::new(data)).map(|decoder| {
+            if decoder.has_animation() {
+                decoder.into_frames().take_while(Result::is_ok).count()
+            } else {
+                1
+            }
+        }),
+        _ => Ok(1),
+    };
+    frames.unwrap_or(1).max(1) as u32
+}
@@ -0,0 +1,169 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// Colors of a palette, fewer if the image has fewer distinct colors
+pub const PALETTE_SIZE: usize = 5;
+/// Edge length the image is downscaled to before quantizing
+const SAMPLE_SIZE: u32 = 64;
+/// Colors less saturated or darker than this have no meaningful hue
+const MIN_SATURATION: f32 = 0.2;
+const MIN_VALUE: f32 = 0.15;
+/// How much farther a color counts that covers none of the image, relative to one covering all of it
+const SHARE_PENALTY: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, bitcode::Encode, bitcode::Decode)]
+pub struct PaletteColor {
+    pub rgb: [u8; 3],
+    /// Fraction of the image this color stands for
+    pub share: f32,
+}
+
+impl PaletteColor {
+    /// Hue in degrees, None for grays and very dark colors
+    pub fn hue(&self) -> Option<f32> {
+        let (hue, saturation, value) = rgb_to_hsv(self.rgb);
+        (saturation >= MIN_SATURATION && value >= MIN_VALUE).then_some(hue)
+    }
+}
+
+/// The dominant colors of an image, quantized with median cut
+#[derive(Debug, Clone, PartialEq, bitcode::Encode, bitcode::Decode)]
+pub struct ColorPalette {
+    /// The largest share first
+    pub colors: Vec<PaletteColor>,
+    /// Mean luma from 0 to 1
+    pub brightness: f32,
+}
+
+impl ColorPalette {
+    pub fn of_image(image: &DynamicImage) -> Self {
+        let sample = image
+            .resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle)
+            .to_rgb8();
+        let pixels: Vec<[u8; 3]> = sample.pixels().map(|pixel| pixel.0).collect();
+        if pixels.is_empty() {
+            return Self {
+                colors: Vec::new(),
+                brightness: 0.0,
+            };
+        }
+
+        let brightness = pixels.iter().map(|rgb| luma(*rgb)).sum::<f32>() / pixels.len() as f32;
+        let total = pixels.len() as f32;
+        // Splitting at the median can leave the same color in both halves, those are merged
+        let mut colors: Vec<PaletteColor> = Vec::new();
+        for bucket in median_cut(pixels, PALETTE_SIZE) {
+            let rgb = average(&bucket);
+            let share = bucket.len() as f32 / total;
+            match colors.iter_mut().find(|color| color.rgb == rgb) {
+                Some(color) => color.share += share,
+                None => colors.push(PaletteColor { rgb, share }),
+            }
+        }
+        colors.sort_by(|a, b| b.share.total_cmp(&a.share));
+
+        Self { colors, brightness }
+    }
+
+    /// Hue of the most dominant colorful color. Astronomy images are mostly black,
+    /// so grays and dark colors are skipped, None if there are only those.
+    pub fn hue(&self) -> Option<f32> {
+        self.colors.iter().find_map(PaletteColor::hue)
+    }
+
+    /// How far the closest palette color is from the color, from 0 for a perfect match.
+    /// Colors covering less of the image count as farther away.
+    pub fn distance(&self, rgb: [u8; 3]) -> f32 {
+        self.colors
+            .iter()
+            .map(|color| color_distance(color.rgb, rgb) + (1.0 - color.share) * SHARE_PENALTY)
+            .min_by(f32::total_cmp)
+            .unwrap_or(f32::INFINITY)
+    }
+}
+
+/// Splits the pixels into up to `count` buckets, always halving the bucket
+/// with the widest channel range at the median of that channel
+fn median_cut(pixels: Vec<[u8; 3]>, count: usize) -> Vec<Vec<[u8; 3]>> {
+    let mut buckets = vec![pixels];
+    while buckets.len() < count {
+        let Some((index, channel, range)) = buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, bucket)| bucket.len() > 1)
+            .map(|(index, bucket)| {
+                let (channel, range) = widest_channel(bucket);
+                (index, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range)
+        else {
+            break;
+        };
+        if range == 0 {
+            break;
+        }
+
+        let mut bucket = buckets.swap_remove(index);
+        bucket.sort_unstable_by_key(|rgb| rgb[channel]);
+        let upper = bucket.split_off(bucket.len() / 2);
+        buckets.push(bucket);
+        buckets.push(upper);
+    }
+    buckets
+}
+
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = pixels.iter().map(|rgb| rgb[channel]).min().unwrap_or(0);
+            let max = pixels.iter().map(|rgb| rgb[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[[u8; 3]]) -> [u8; 3] {
+    let mut sums = [0u64; 3];
+    for rgb in pixels {
+        for (sum, value) in sums.iter_mut().zip(rgb) {
+            *sum += *value as u64;
+        }
+    }
+    sums.map(|sum| (sum / pixels.len().max(1) as u64) as u8)
+}
+
+/// Rec. 601 luma from 0 to 1
+fn luma([r, g, b]: [u8; 3]) -> f32 {
+    (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) / 255.0
+}
+
+/// The "redmean" approximation of perceived difference, from 0 to about 1
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let red_mean = (a[0] as f32 + b[0] as f32) / 2.0;
+    let [dr, dg, db] = [0, 1, 2].map(|i| a[i] as f32 - b[i] as f32);
+    let squared = (2.0 + red_mean / 256.0) * dr * dr
+        + 4.0 * dg * dg
+        + (2.0 + (255.0 - red_mean) / 256.0) * db * db;
+    squared.sqrt() / 765.0
+}
+
+/// Hue in degrees, saturation and value from 0 to 1
+pub fn rgb_to_hsv(rgb: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = rgb.map(|value| value as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, saturation, max)
+}
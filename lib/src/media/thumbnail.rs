@@ -0,0 +1,152 @@
+use crate::date::ApodDate;
+use crate::media::{MediaCache, MediaEntry, MediaType, MediaVariant};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::DynamicImage;
+use std::error::Error;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThumbnailError {
+    #[error("Cache error: {0}")]
+    Cache(Box<dyn Error + Send + Sync>),
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("No cached media for {0}")]
+    NoMedia(ApodDate),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThumbnailFormat {
+    /// Lossy, quality from 1 to 100
+    Jpeg { quality: u8 },
+    /// Lossless, larger than JPEG but keeps sharp edges and transparency
+    WebP,
+}
+
+impl Default for ThumbnailFormat {
+    fn default() -> Self {
+        Self::Jpeg { quality: 80 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThumbnailOptions {
+    /// Longest edges in pixels, one variant is stored per size
+    pub sizes: Vec<u16>,
+    pub format: ThumbnailFormat,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            sizes: vec![128, 256, 512],
+            format: ThumbnailFormat::default(),
+        }
+    }
+}
+
+impl ThumbnailOptions {
+    /// The smallest size that is at least as large as requested, or the largest one
+    pub fn size_for(&self, pixels: f32) -> Option<u16> {
+        let mut sizes = self.sizes.clone();
+        sizes.sort();
+        sizes
+            .iter()
+            .find(|size| **size as f32 >= pixels)
+            .or(sizes.last())
+            .copied()
+    }
+}
+
+/// Downscales the image keeping its aspect ratio, images that are already small enough are only re-encoded
+pub fn create_thumbnail(
+    image: &DynamicImage,
+    size: u16,
+    format: ThumbnailFormat,
+) -> Result<MediaEntry, ThumbnailError> {
+    let size = size as u32;
+    let thumbnail = if image.width() > size || image.height() > size {
+        image.thumbnail(size, size)
+    } else {
+        image.clone()
+    };
+
+    let mut data = Vec::new();
+    let media_type = match format {
+        ThumbnailFormat::Jpeg { quality } => {
+            let encoder = JpegEncoder::new_with_quality(&mut data, quality.clamp(1, 100));
+            DynamicImage::ImageRgb8(thumbnail.to_rgb8()).write_with_encoder(encoder)?;
+            MediaType::ImageJPEG
+        }
+        ThumbnailFormat::WebP => {
+            let encoder = WebPEncoder::new_lossless(&mut data);
+            DynamicImage::ImageRgba8(thumbnail.to_rgba8()).write_with_encoder(encoder)?;
+            MediaType::ImageWEBP
+        }
+    };
+
+    Ok(MediaEntry { media_type, data })
+}
+
+/// Decodes the best cached media once and stores a thumbnail variant for every configured size,
+/// from the poster if the media is not an image. Returns the number of stored thumbnails.
+pub fn generate_thumbnails(
+    cache: &mut dyn MediaCache,
+    date: ApodDate,
+    options: &ThumbnailOptions,
+) -> Result<usize, ThumbnailError> {
+    let original = match cache.get(date).map_err(ThumbnailError::Cache)? {
+        Some(original) if original.media_type.is_image() => Some(original),
+        _ => cache
+            .get_variant(date, MediaVariant::Poster)
+            .map_err(ThumbnailError::Cache)?,
+    }
+    .ok_or(ThumbnailError::NoMedia(date))?;
+    let image = image::load_from_memory(&original.data)?;
+
+    for size in &options.sizes {
+        let thumbnail = create_thumbnail(&image, *size, options.format)?;
+        cache
+            .store_variant(
+                date,
+                MediaVariant::Thumbnail(*size),
+                &thumbnail.data,
+                thumbnail.media_type,
+            )
+            .map_err(ThumbnailError::Cache)?;
+    }
+
+    Ok(options.sizes.len())
+}
+
+/// Returns the cached thumbnail, generating all configured sizes from the full size media if it is missing
+pub fn get_or_generate_thumbnail(
+    cache: &mut dyn MediaCache,
+    date: ApodDate,
+    size: u16,
+    options: &ThumbnailOptions,
+) -> Result<MediaEntry, ThumbnailError> {
+    if let Some(thumbnail) = cache
+        .get_thumbnail(date, size)
+        .map_err(ThumbnailError::Cache)?
+    {
+        return Ok(thumbnail);
+    }
+
+    if options.sizes.contains(&size) {
+        generate_thumbnails(cache, date, options)?;
+    } else {
+        let single = ThumbnailOptions {
+            sizes: vec![size],
+            format: options.format,
+        };
+        generate_thumbnails(cache, date, &single)?;
+    }
+
+    cache
+        .get_thumbnail(date, size)
+        .map_err(ThumbnailError::Cache)?
+        .ok_or(ThumbnailError::NoMedia(date))
+}
@@ -0,0 +1,132 @@
+use crate::date::ApodDate;
+use crate::media::{MediaCache, MediaVariant};
+use std::error::Error;
+use std::fmt::Display;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error("Cache error: {0}")]
+    Cache(Box<dyn Error + Send + Sync>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaProblem {
+    /// Listed but gone when read
+    Missing,
+    /// Failed to read, e.g. the data doesn't match its stored checksum
+    Unreadable(String),
+    /// A web page saved in place of the media, usually an error page of the server
+    Html,
+    /// Labeled as an image but can't be decoded, e.g. a truncated download
+    Undecodable(String),
+}
+
+impl Display for MediaProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaProblem::Missing => write!(f, "Missing"),
+            MediaProblem::Unreadable(err) => write!(f, "Unreadable: {err}"),
+            MediaProblem::Html => write!(f, "HTML page instead of media"),
+            MediaProblem::Undecodable(err) => write!(f, "Can't be decoded: {err}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptMedia {
+    pub date: ApodDate,
+    pub variant: MediaVariant,
+    pub problem: MediaProblem,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub corrupt: Vec<CorruptMedia>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.corrupt.is_empty()
+    }
+
+    /// Corrupt media that can be downloaded again, thumbnails are generated from it instead
+    pub fn downloadable(&self) -> Vec<(ApodDate, MediaVariant)> {
+        self.corrupt
+            .iter()
+            .filter(|corrupt| corrupt.variant.is_full_size())
+            .map(|corrupt| (corrupt.date, corrupt.variant))
+            .collect()
+    }
+
+    /// Deletes all corrupt media from the cache, returns how many were deleted
+    pub fn delete_corrupt<M: MediaCache + ?Sized>(
+        &self,
+        cache: &mut M,
+    ) -> Result<usize, VerifyError> {
+        let mut deleted = 0;
+        for corrupt in &self.corrupt {
+            if cache
+                .delete_variant(corrupt.date, corrupt.variant)
+                .map_err(VerifyError::Cache)?
+            {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Reads every cached file and decodes every image, without counting as an access.
+/// Reads are verified by caches that store checksums, decoding also catches media
+/// that was already broken when it was downloaded. Reports progress as (checked, total).
+pub fn verify_cache<M: MediaCache + ?Sized>(
+    cache: &M,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<VerifyReport, VerifyError> {
+    let media = cache.list().map_err(VerifyError::Cache)?;
+    let mut report = VerifyReport::default();
+
+    for cached in &media {
+        let problem = match cache.peek_variant(cached.date, cached.variant) {
+            Ok(Some(entry)) => check_media(&entry.data, entry.media_type.is_image()),
+            Ok(None) => Some(MediaProblem::Missing),
+            Err(err) => Some(MediaProblem::Unreadable(err.to_string())),
+        };
+        if let Some(problem) = problem {
+            report.corrupt.push(CorruptMedia {
+                date: cached.date,
+                variant: cached.variant,
+                problem,
+            });
+        }
+
+        report.checked += 1;
+        on_progress(report.checked, media.len());
+    }
+
+    Ok(report)
+}
+
+/// Media that is not labeled as an image, like videos, is only checked for being a web page
+pub fn check_media(data: &[u8], is_image: bool) -> Option<MediaProblem> {
+    if is_html(data) {
+        return Some(MediaProblem::Html);
+    }
+    if is_image && let Err(err) = image::load_from_memory(data) {
+        return Some(MediaProblem::Undecodable(err.to_string()));
+    }
+    None
+}
+
+fn is_html(data: &[u8]) -> bool {
+    let start = data
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    let head = &data[start..data.len().min(start + 64)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    head.starts_with("<!doctype html")
+        || head.starts_with("<html")
+        || (head.starts_with("<?xml") && head.contains("html"))
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+/// Duration from the movie header of an MP4 file, None if it's missing or the file is truncated
+pub fn mp4_duration(data: &[u8]) -> Option<Duration> {
+    let movie = find_box(data, b"moov")?;
+    let header = find_box(movie, b"mvhd")?;
+    // Version and flags, then creation and modification times in 32 or 64 bits
+    let (timescale, duration) = match header.first()? {
+        0 => (read_u32(header, 12)?, read_u32(header, 16)? as u64),
+        1 => (read_u32(header, 20)?, read_u64(header, 24)?),
+        _ => return None,
+    };
+
+    // All bits set means the duration is unknown
+    let unknown = duration == u64::MAX || (header[0] == 0 && duration == u32::MAX as u64);
+    if timescale == 0 || unknown {
+        return None;
+    }
+    Some(Duration::from_secs_f64(duration as f64 / timescale as f64))
+}
+
+/// Content of the first box of the type among the consecutive boxes in the data
+fn find_box<'a>(mut data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    while data.len() >= 8 {
+        let (header_size, size) = match read_u32(data, 0)? {
+            // 64 bit size after the type
+            1 => (16, read_u64(data, 8)?),
+            // Extends to the end of the file
+            0 => (8, data.len() as u64),
+            size => (8, size as u64),
+        };
+        if size < header_size || size > data.len() as u64 {
+            return None;
+        }
+
+        let (current, rest) = data.split_at(size as usize);
+        if &current[4..8] == box_type {
+            return Some(&current[header_size as usize..]);
+        }
+        data = rest;
+    }
+    None
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
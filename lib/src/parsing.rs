@@ -3,12 +3,13 @@ use crate::ApodEntry;
 use scraper::Html;
 
 mod explanation;
+pub mod extras;
 pub mod media_url;
 pub mod quality_control;
 mod title;
 pub mod verbose;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParseError {
     #[error("Explanation not found")]
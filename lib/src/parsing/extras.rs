@@ -0,0 +1,114 @@
+use crate::date::ApodDate;
+use crate::APOD_BASE_URL;
+use scraper::{Html, Selector};
+
+/// Information of an APOD page that is not part of the entry itself
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryExtras {
+    pub credits: Option<String>,
+    /// Links inside the explanation, in order of appearance
+    pub links: Vec<EntryLink>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryLink {
+    pub text: String,
+    /// Always absolute, relative links are resolved against the APOD base url
+    pub url: String,
+}
+
+impl EntryLink {
+    /// Date of the linked APOD page, for links between entries
+    pub fn apod_date(&self) -> Option<ApodDate> {
+        let (host, page) = self.url.rsplit_once("/apod/")?;
+        if !host.ends_with("nasa.gov") {
+            return None;
+        }
+
+        let code = page.strip_prefix("ap")?.strip_suffix(".html")?;
+        ApodDate::from_page_code(code)
+    }
+}
+
+pub fn parse_extras(html: &str) -> EntryExtras {
+    let doc = Html::parse_document(html);
+    EntryExtras {
+        credits: parse_credits(&doc),
+        links: parse_explanation_links(html),
+    }
+}
+
+/// Credits are the text between the credit label and the explanation,
+/// e.g. `<b> Image Credit & Copyright: </b> Christopher Go`
+fn parse_credits(doc: &Html) -> Option<String> {
+    let full_text = doc.root_element().text().collect::<String>();
+    let before_explanation = full_text.split("Explanation:").next()?;
+    let (_, after_label) = before_explanation.split_once("Credit")?;
+    let (_, credits) = after_label.split_once(':')?;
+
+    let credits = credits.split_whitespace().collect::<Vec<_>>().join(" ");
+    if credits.is_empty() {
+        None
+    } else {
+        Some(credits)
+    }
+}
+
+fn parse_explanation_links(html: &str) -> Vec<EntryLink> {
+    let Some((_, explanation)) = html.split_once("Explanation:") else {
+        return Vec::new();
+    };
+
+    let delimiters = [
+        "Tomorrow's picture",
+        "Tomorrow's Picture",
+        "Authors & editors",
+        "<hr",
+    ];
+    let end = delimiters
+        .iter()
+        .filter_map(|delim| explanation.find(delim))
+        .min()
+        .unwrap_or(explanation.len());
+
+    let fragment = Html::parse_fragment(&explanation[..end]);
+    let a_sel = Selector::parse("a[href]").unwrap();
+
+    fragment
+        .select(&a_sel)
+        .filter_map(|link| {
+            let href = link.value().attr("href")?;
+            let url = resolve_url(href)?;
+            let text = link.text().collect::<Vec<_>>().join(" ");
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            Some(EntryLink { text, url })
+        })
+        .collect()
+}
+
+fn resolve_url(href: &str) -> Option<String> {
+    // Long links are sometimes wrapped across lines inside the attribute
+    let href: String = href.split_whitespace().collect();
+    if href.is_empty() || href.starts_with('#') || href.starts_with("mailto:") {
+        return None;
+    }
+
+    if href.starts_with("http://") || href.starts_with("https://") {
+        Some(href)
+    } else if let Some(rest) = href.strip_prefix("//") {
+        Some(format!("https://{}", rest))
+    } else if href.starts_with('/') {
+        let host = APOD_BASE_URL.trim_end_matches("/apod");
+        Some(format!("{}{}", host, href))
+    } else {
+        Some(format!(
+            "{}/{}",
+            APOD_BASE_URL,
+            href.trim_start_matches("./")
+        ))
+    }
+}
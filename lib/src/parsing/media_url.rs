@@ -1,7 +1,25 @@
+use crate::media::MediaVariant;
 use crate::parsing::ParseError;
 use crate::APOD_BASE_URL;
 use regex::Regex;
 use scraper::{Html, Selector};
+use std::fmt::Display;
+use std::sync::LazyLock;
+
+static YOUTUBE_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:https?:)?//(?:www\.|m\.)?(?:youtube(?:-nocookie)?\.com/(?:embed/|v/|shorts/|watch\?(?:\S*&)?v=)|youtu\.be/)([\w-]+)").unwrap()
+});
+static VIMEO_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:https?:)?//(?:www\.|player\.)?vimeo\.com/(?:video/)?(\d+)").unwrap()
+});
+static DAILYMOTION_ID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:https?:)?//(?:www\.)?(?:dailymotion\.com/(?:embed/)?video/|dai\.ly/)([a-zA-Z0-9]+)",
+    )
+    .unwrap()
+});
+static OEMBED_THUMBNAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""thumbnail_url"\s*:\s*"([^"]+)""#).unwrap());
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
@@ -18,6 +36,53 @@ pub enum MediaUrlKind {
     ImageGIF,
     VideoMP4,
     YoutubeVideo,
+    VimeoVideo,
+    DailymotionVideo,
+}
+
+impl Display for MediaUrlKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaUrlKind::ImagePNG => write!(f, "png"),
+            MediaUrlKind::ImageJPG => write!(f, "jpg"),
+            MediaUrlKind::ImageGIF => write!(f, "gif"),
+            MediaUrlKind::VideoMP4 => write!(f, "mp4"),
+            MediaUrlKind::YoutubeVideo => write!(f, "youtube"),
+            MediaUrlKind::VimeoVideo => write!(f, "vimeo"),
+            MediaUrlKind::DailymotionVideo => write!(f, "dailymotion"),
+        }
+    }
+}
+
+impl MediaUrlKind {
+    /// None for embedded videos, which are not a file
+    pub fn mime_type(&self) -> Option<&'static str> {
+        match self {
+            MediaUrlKind::ImagePNG => Some("image/png"),
+            MediaUrlKind::ImageJPG => Some("image/jpeg"),
+            MediaUrlKind::ImageGIF => Some("image/gif"),
+            MediaUrlKind::VideoMP4 => Some("video/mp4"),
+            MediaUrlKind::YoutubeVideo
+            | MediaUrlKind::VimeoVideo
+            | MediaUrlKind::DailymotionVideo => None,
+        }
+    }
+
+    pub fn is_video(&self) -> bool {
+        !self.is_image()
+    }
+
+    /// Videos played on the page of their host, see [`EmbeddedVideo`]
+    pub fn is_embedded_video(&self) -> bool {
+        self.is_video() && *self != MediaUrlKind::VideoMP4
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(
+            self,
+            MediaUrlKind::ImagePNG | MediaUrlKind::ImageJPG | MediaUrlKind::ImageGIF
+        )
+    }
 }
 
 impl MediaUrl {
@@ -25,11 +90,37 @@ impl MediaUrl {
         self.hd_url.as_deref().or(self.url.as_deref())
     }
 
+    /// Where a full size variant is fetched from, other variants are generated locally.
+    /// Embedded videos have no file to fetch, their poster is fetched from the host instead.
+    pub fn variant_url(&self, variant: MediaVariant) -> Option<&str> {
+        if self.embedded_video().is_some() {
+            return None;
+        }
+        match variant {
+            MediaVariant::Standard => self.url.as_deref(),
+            MediaVariant::Hd => self.highest_quality(),
+            MediaVariant::Thumbnail(_) | MediaVariant::Poster => None,
+        }
+    }
+
+    /// Whether there is a separate HD version besides the standard media
+    pub fn has_hd(&self) -> bool {
+        self.hd_url.is_some() && self.hd_url != self.url
+    }
+
+    pub fn embedded_video(&self) -> Option<EmbeddedVideo> {
+        EmbeddedVideo::parse(self.url.as_deref()?)
+    }
+
     pub fn kind(&self) -> Option<MediaUrlKind> {
         let url = self.url.as_deref()?;
 
-        if url.starts_with("https://www.youtube.com/embed/") {
-            return Some(MediaUrlKind::YoutubeVideo);
+        if let Some(video) = EmbeddedVideo::parse(url) {
+            return Some(match video.host {
+                VideoHost::Youtube => MediaUrlKind::YoutubeVideo,
+                VideoHost::Vimeo => MediaUrlKind::VimeoVideo,
+                VideoHost::Dailymotion => MediaUrlKind::DailymotionVideo,
+            });
         }
 
         let path = url.split('?').next().unwrap_or(url);
@@ -45,6 +136,88 @@ impl MediaUrl {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoHost {
+    Youtube,
+    Vimeo,
+    Dailymotion,
+}
+
+impl Display for VideoHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoHost::Youtube => write!(f, "YouTube"),
+            VideoHost::Vimeo => write!(f, "Vimeo"),
+            VideoHost::Dailymotion => write!(f, "Dailymotion"),
+        }
+    }
+}
+
+/// A video on a hosting site, identified by its ID there
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmbeddedVideo {
+    pub host: VideoHost,
+    pub id: String,
+}
+
+impl EmbeddedVideo {
+    /// Recognizes embed URLs as well as links to watch pages and short links
+    pub fn parse(url: &str) -> Option<Self> {
+        let hosts = [
+            (VideoHost::Youtube, &YOUTUBE_ID),
+            (VideoHost::Vimeo, &VIMEO_ID),
+            (VideoHost::Dailymotion, &DAILYMOTION_ID),
+        ];
+        hosts.into_iter().find_map(|(host, regex)| {
+            let id = regex.captures(url)?.get(1)?.as_str().to_string();
+            Some(Self { host, id })
+        })
+    }
+
+    /// The page to watch the video on
+    pub fn watch_url(&self) -> String {
+        match self.host {
+            VideoHost::Youtube => format!("https://www.youtube.com/watch?v={}", self.id),
+            VideoHost::Vimeo => format!("https://vimeo.com/{}", self.id),
+            VideoHost::Dailymotion => format!("https://www.dailymotion.com/video/{}", self.id),
+        }
+    }
+
+    /// Still images of the video at known URLs, the best first
+    pub fn poster_urls(&self) -> Vec<String> {
+        match self.host {
+            VideoHost::Youtube => ["maxresdefault", "hqdefault"]
+                .map(|name| format!("https://img.youtube.com/vi/{}/{}.jpg", self.id, name))
+                .to_vec(),
+            VideoHost::Vimeo => Vec::new(),
+            VideoHost::Dailymotion => {
+                vec![format!(
+                    "https://www.dailymotion.com/thumbnail/video/{}",
+                    self.id
+                )]
+            }
+        }
+    }
+
+    /// For hosts whose posters have unpredictable URLs, the response names them,
+    /// see [`parse_oembed_thumbnail`]
+    pub fn oembed_url(&self) -> Option<String> {
+        match self.host {
+            VideoHost::Vimeo => Some(format!(
+                "https://vimeo.com/api/oembed.json?url=https%3A%2F%2Fvimeo.com%2F{}&width=1280",
+                self.id
+            )),
+            VideoHost::Youtube | VideoHost::Dailymotion => None,
+        }
+    }
+}
+
+/// The thumbnail URL of an oEmbed JSON response
+pub fn parse_oembed_thumbnail(json: &str) -> Option<String> {
+    let url = OEMBED_THUMBNAIL.captures(json)?.get(1)?.as_str();
+    Some(url.replace("\\/", "/"))
+}
+
 pub fn parse_media(doc: &Html) -> Result<MediaUrl, ParseError> {
     if let Some((url, hd_url)) = extract_image_urls(doc) {
         return Ok(MediaUrl {
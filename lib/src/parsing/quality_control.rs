@@ -1,6 +1,7 @@
 use crate::parsing::media_url::MediaUrl;
 use crate::ApodEntry;
 use std::collections::HashSet;
+use std::fmt::Display;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -14,6 +15,20 @@ pub enum QualityWarning {
     UnknownMediaKind,
 }
 
+impl Display for QualityWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QualityWarning::ContainsHtml => write!(f, "contains_html"),
+            QualityWarning::EmptyField => write!(f, "empty_field"),
+            QualityWarning::LeadingWhitespace => write!(f, "leading_whitespace"),
+            QualityWarning::MultiWhitespace => write!(f, "multi_whitespace"),
+            QualityWarning::TrailingWhitespace => write!(f, "trailing_whitespace"),
+            QualityWarning::TitleMultiline => write!(f, "title_multiline"),
+            QualityWarning::UnknownMediaKind => write!(f, "unknown_media_kind"),
+        }
+    }
+}
+
 pub fn quality_control(entry: &ApodEntry) -> HashSet<QualityWarning> {
     let mut warnings = HashSet::new();
     quality_control_title(&entry.title, &mut warnings);
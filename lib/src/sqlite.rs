@@ -0,0 +1,306 @@
+use crate::archiving::Archive;
+use crate::date::ApodDate;
+use crate::parsing::extras::{EntryExtras, EntryLink};
+use crate::parsing::media_url::MediaUrl;
+use crate::parsing::quality_control::quality_control;
+use crate::parsing::ParseError;
+use crate::ApodEntry;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Entries are keyed by their day count since the first APOD, which is also the FTS5 rowid.
+/// Dates are additionally stored as ISO text for querying.
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS entries (
+    id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    explanation TEXT NOT NULL,
+    media_url TEXT,
+    hd_url TEXT,
+    media_kind TEXT
+);
+
+CREATE TABLE IF NOT EXISTS warnings (
+    entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+    warning TEXT NOT NULL,
+    PRIMARY KEY (entry_id, warning)
+);
+
+CREATE TABLE IF NOT EXISTS credits (
+    entry_id INTEGER PRIMARY KEY REFERENCES entries(id) ON DELETE CASCADE,
+    credits TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS links (
+    entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    url TEXT NOT NULL,
+    PRIMARY KEY (entry_id, position)
+);
+
+-- Dates whose page could not be parsed into an entry, so they are not tied to the entries table
+CREATE TABLE IF NOT EXISTS errors (
+    id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL UNIQUE,
+    error TEXT NOT NULL
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS entries_fts USING fts5(
+    title,
+    explanation,
+    content = 'entries',
+    content_rowid = 'id'
+);
+
+CREATE TRIGGER IF NOT EXISTS entries_fts_insert AFTER INSERT ON entries BEGIN
+    INSERT INTO entries_fts(rowid, title, explanation)
+    VALUES (new.id, new.title, new.explanation);
+END;
+
+CREATE TRIGGER IF NOT EXISTS entries_fts_delete AFTER DELETE ON entries BEGIN
+    INSERT INTO entries_fts(entries_fts, rowid, title, explanation)
+    VALUES ('delete', old.id, old.title, old.explanation);
+END;
+
+CREATE TRIGGER IF NOT EXISTS entries_fts_update AFTER UPDATE ON entries BEGIN
+    INSERT INTO entries_fts(entries_fts, rowid, title, explanation)
+    VALUES ('delete', old.id, old.title, old.explanation);
+    INSERT INTO entries_fts(rowid, title, explanation)
+    VALUES (new.id, new.title, new.explanation);
+END;
+"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SqliteError {
+    #[error("Invalid date '{0}' in database")]
+    InvalidDate(String),
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// A normalized SQLite database of entries, usable as an alternative to the bitcode archive.
+/// Writing upserts by date, entries of dates that are not written are kept.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, SqliteError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Opens an existing database for reading only, without creating the schema,
+    /// so the file is never modified, e.g. when importing from it
+    pub fn open_read_only(path: &Path) -> Result<Self, SqliteError> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        Ok(Self {
+            conn: Connection::open_with_flags(path, flags)?,
+        })
+    }
+
+    pub fn open_in_memory() -> Result<Self, SqliteError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, SqliteError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Writes all entries together with their quality warnings
+    pub fn write_entries(&mut self, archive: &Archive<ApodEntry>) -> Result<(), SqliteError> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut upsert_entry = tx.prepare(
+                "INSERT INTO entries (id, date, title, explanation, media_url, hd_url, media_kind)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT(id) DO UPDATE SET
+                    date = excluded.date,
+                    title = excluded.title,
+                    explanation = excluded.explanation,
+                    media_url = excluded.media_url,
+                    hd_url = excluded.hd_url,
+                    media_kind = excluded.media_kind",
+            )?;
+            let mut delete_warnings = tx.prepare("DELETE FROM warnings WHERE entry_id = ?1")?;
+            let mut insert_warning =
+                tx.prepare("INSERT INTO warnings (entry_id, warning) VALUES (?1, ?2)")?;
+            let mut delete_error = tx.prepare("DELETE FROM errors WHERE id = ?1")?;
+
+            for (date, entry) in archive.iter() {
+                upsert_entry.execute(params![
+                    date.days(),
+                    date.to_string(),
+                    entry.title,
+                    entry.explanation,
+                    entry.media.url,
+                    entry.media.hd_url,
+                    entry.media.kind().map(|kind| kind.to_string()),
+                ])?;
+
+                delete_warnings.execute([date.days()])?;
+                for warning in quality_control(entry) {
+                    insert_warning.execute(params![date.days(), warning.to_string()])?;
+                }
+
+                delete_error.execute([date.days()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn write_errors(
+        &mut self,
+        errors: &HashMap<ApodDate, ParseError>,
+    ) -> Result<(), SqliteError> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut upsert_error = tx.prepare(
+                "INSERT INTO errors (id, date, error) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET error = excluded.error",
+            )?;
+            for (date, error) in errors {
+                upsert_error.execute(params![date.days(), date.to_string(), error.to_string()])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Extras of dates without a written entry are skipped
+    pub fn write_extras(
+        &mut self,
+        extras: &HashMap<ApodDate, EntryExtras>,
+    ) -> Result<(), SqliteError> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut has_entry = tx.prepare("SELECT 1 FROM entries WHERE id = ?1")?;
+            let mut delete_credits = tx.prepare("DELETE FROM credits WHERE entry_id = ?1")?;
+            let mut insert_credits =
+                tx.prepare("INSERT INTO credits (entry_id, credits) VALUES (?1, ?2)")?;
+            let mut delete_links = tx.prepare("DELETE FROM links WHERE entry_id = ?1")?;
+            let mut insert_link = tx.prepare(
+                "INSERT INTO links (entry_id, position, text, url) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+
+            for (date, extras) in extras {
+                if !has_entry.exists([date.days()])? {
+                    continue;
+                }
+
+                delete_credits.execute([date.days()])?;
+                if let Some(credits) = &extras.credits {
+                    insert_credits.execute(params![date.days(), credits])?;
+                }
+
+                delete_links.execute([date.days()])?;
+                for (position, link) in extras.links.iter().enumerate() {
+                    insert_link.execute(params![
+                        date.days(),
+                        position as i64,
+                        link.text,
+                        link.url
+                    ])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn read_entries(&self) -> Result<Archive<ApodEntry>, SqliteError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT date, title, explanation, media_url, hd_url FROM entries")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })?;
+
+        let mut archive = Archive::default();
+        for row in rows {
+            let (date, title, explanation, url, hd_url) = row?;
+            archive.push(ApodEntry {
+                date: parse_date(&date)?,
+                title,
+                explanation,
+                media: MediaUrl { url, hd_url },
+            });
+        }
+
+        Ok(archive)
+    }
+
+    pub fn read_errors(&self) -> Result<HashMap<ApodDate, String>, SqliteError> {
+        let mut stmt = self.conn.prepare("SELECT date, error FROM errors")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut errors = HashMap::new();
+        for row in rows {
+            let (date, error) = row?;
+            errors.insert(parse_date(&date)?, error);
+        }
+
+        Ok(errors)
+    }
+
+    pub fn read_extras(&self, date: ApodDate) -> Result<EntryExtras, SqliteError> {
+        let credits = self
+            .conn
+            .query_row(
+                "SELECT credits FROM credits WHERE entry_id = ?1",
+                [date.days()],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT text, url FROM links WHERE entry_id = ?1 ORDER BY position")?;
+        let links = stmt
+            .query_map([date.days()], |row| {
+                Ok(EntryLink {
+                    text: row.get(0)?,
+                    url: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(EntryExtras { credits, links })
+    }
+
+    /// Full text search over titles and explanations using the FTS5 query syntax, best matches first
+    pub fn search(&self, query: &str) -> Result<Vec<ApodDate>, SqliteError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT entries.date FROM entries_fts
+             JOIN entries ON entries.id = entries_fts.rowid
+             WHERE entries_fts MATCH ?1
+             ORDER BY rank",
+        )?;
+        let dates = stmt
+            .query_map([query], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        dates.iter().map(|date| parse_date(date)).collect()
+    }
+}
+
+fn parse_date(date: &str) -> Result<ApodDate, SqliteError> {
+    ApodDate::from_iso(date).ok_or_else(|| SqliteError::InvalidDate(date.to_string()))
+}